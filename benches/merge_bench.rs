@@ -1,8 +1,10 @@
 use criterion::{Criterion, criterion_group, criterion_main};
-use ipnet::Ipv4Net;
+use ipnet::{IpNet, Ipv4Net};
 use std::hint::black_box;
 
-use clpsr::merge_ipv4_nets;
+use clpsr::{
+    MergeOptions, MergeStrategy, ToleranceMode, merge_ip_nets_with_options, merge_ipv4_nets,
+};
 
 /// Generates a vector of adjacent networks that can be merged.
 ///
@@ -134,6 +136,34 @@ fn bench_merge_with_tolerance(c: &mut Criterion) {
     });
 }
 
+/// Benchmarks greedy and optimal merging of 1000 networks within a total budget.
+///
+/// Generates /26 networks spaced one /26 apart, so that every merge costs extra
+/// addresses and the strategies have to choose which merges to spend the budget on.
+fn bench_merge_strategies_with_budget(c: &mut Criterion) {
+    let nets: Vec<IpNet> = (0..1000u32)
+        .map(|i| {
+            let addr = (10u32 << 24) | (i * 128);
+            IpNet::V4(Ipv4Net::new(addr.into(), 26).unwrap())
+        })
+        .collect();
+    let greedy = MergeOptions {
+        tolerance_v4: 64 * 100,
+        tolerance_mode: ToleranceMode::Budget,
+        ..MergeOptions::default()
+    };
+    let optimal = MergeOptions {
+        strategy: MergeStrategy::Optimal,
+        ..greedy.clone()
+    };
+    c.bench_function("merge_1000_networks_greedy_budget", |b| {
+        b.iter(|| merge_ip_nets_with_options(black_box(nets.clone()), &greedy))
+    });
+    c.bench_function("merge_1000_networks_optimal_budget", |b| {
+        b.iter(|| merge_ip_nets_with_options(black_box(nets.clone()), &optimal))
+    });
+}
+
 /// Benchmarks merging 110 networks containing covered subnets.
 ///
 /// Generates a mix of /16 supernets and their /24 subnets to test the
@@ -198,6 +228,7 @@ criterion_group!(
     bench_merge_mergeable_pairs,
     bench_merge_non_mergeable,
     bench_merge_with_tolerance,
+    bench_merge_strategies_with_budget,
     bench_merge_with_covered_subnets,
     bench_merge_iterative_scenario
 );
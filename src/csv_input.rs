@@ -0,0 +1,334 @@
+//! CSV input parsing.
+//!
+//! Inventory exports often hold a network in one column and metadata in the others.
+//! [`parse_csv_ip_nets`] reads the network from the selected column, accepting the same
+//! notations as the line-per-CIDR parser, and keeps every other non-empty field as an
+//! annotation so that it can be carried through the merge. Quoted fields (including
+//! ones with embedded delimiters, quotes or line breaks) are handled by the `csv` crate.
+
+use std::io;
+
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::parse::{host_bits_warning, line_error, parse_entry};
+use crate::{AnnotatedNet, ErrorMode, ParseError, ParseErrorKind, ParseOptions, ParsedRecords};
+
+/// Selects the CSV column holding the networks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    /// The column with this header, compared after trimming whitespace.
+    Name(String),
+    /// The column at this 0-based position.
+    Index(usize),
+}
+
+impl Default for CsvColumn {
+    fn default() -> Self {
+        CsvColumn::Index(0)
+    }
+}
+
+/// Options for [`parse_csv_ip_nets`].
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Column holding the networks.
+    pub column: CsvColumn,
+    /// Whether the first record is a header row rather than data.
+    ///
+    /// Required to select the column by name. Header names also label annotations.
+    pub has_headers: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            column: CsvColumn::default(),
+            has_headers: true,
+        }
+    }
+}
+
+/// Parse IPv4 and IPv6 networks from one column of CSV input.
+///
+/// Each field of the selected column is parsed like a line of
+/// [`parse_ip_nets`](crate::parse_ip_nets), so it may hold a CIDR, a bare address, a
+/// dotted mask or an address range; records whose field is empty are skipped. Every
+/// other non-empty field becomes an annotation of the record's networks, written as
+/// `header=value` when the column has a header and as the bare value otherwise.
+///
+/// Errors report the line on which the record starts, and the text of the selected
+/// field with the column of the problem within it. A missing column, either in the
+/// header or in a short record, is reported as [`ParseErrorKind::MissingColumn`].
+/// `options` controls invalid records and host bits as in
+/// [`parse_ip_nets_with_options`](crate::parse_ip_nets_with_options).
+///
+/// # Errors
+///
+/// Returns the collected errors if any record failed to parse. A missing header
+/// column and malformed input (such as invalid UTF-8) always fail.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{parse_csv_ip_nets, CsvColumn, CsvOptions, ParseOptions};
+/// use std::io::Cursor;
+///
+/// let input = "host,subnet,owner\nweb,10.0.0.0/24,\"Ops, EU\"\n";
+/// let csv = CsvOptions {
+///     column: CsvColumn::Name("subnet".to_string()),
+///     has_headers: true,
+/// };
+/// let parsed = parse_csv_ip_nets(Cursor::new(input), &csv, &ParseOptions::default()).unwrap();
+///
+/// assert_eq!(parsed.nets[0].net.to_string(), "10.0.0.0/24");
+/// assert_eq!(parsed.nets[0].annotations, vec!["host=web", "owner=Ops, EU"]);
+/// ```
+pub fn parse_csv_ip_nets<R: io::Read>(
+    reader: R,
+    csv: &CsvOptions,
+    options: &ParseOptions,
+) -> Result<ParsedRecords, Vec<ParseError>> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+    let mut records = reader.records();
+
+    let headers = if csv.has_headers {
+        match records.next() {
+            Some(record) => Some(record.map_err(|err| vec![csv_error(err)])?),
+            None => Some(StringRecord::new()),
+        }
+    } else {
+        None
+    };
+
+    let index = match &csv.column {
+        CsvColumn::Index(index) => *index,
+        CsvColumn::Name(name) => headers
+            .as_ref()
+            .and_then(|headers| headers.iter().position(|header| header.trim() == name))
+            .ok_or_else(|| vec![missing_column(1, &csv.column)])?,
+    };
+
+    let mut parsed = ParsedRecords::default();
+    let mut errors = Vec::new();
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                // Skipped records are not failures, so only the read error is reported
+                if options.error_mode == ErrorMode::Skip {
+                    errors.clear();
+                }
+                errors.push(csv_error(err));
+                return Err(errors);
+            }
+        };
+        let line = record
+            .position()
+            .map_or(0, |pos| usize::try_from(pos.line()).unwrap_or(usize::MAX));
+
+        let result = match record.get(index) {
+            Some(field) => {
+                parse_entry(field, options.host_bits).map_err(|err| line_error(line, field, err))
+            }
+            None => Err(missing_column(line, &csv.column)),
+        };
+
+        match result {
+            Ok((nets, cleared)) => {
+                if cleared {
+                    parsed
+                        .normalized
+                        .push(host_bits_warning(line, &record[index]));
+                }
+                let annotations = annotations(&record, headers.as_ref(), index);
+                parsed.nets.extend(nets.into_iter().map(|net| AnnotatedNet {
+                    annotations: annotations.clone(),
+                    ..net.into()
+                }));
+            }
+            Err(err) => {
+                errors.push(err);
+                if options.error_mode == ErrorMode::FailFast {
+                    return Err(errors);
+                }
+            }
+        }
+    }
+
+    match options.error_mode {
+        ErrorMode::Skip => parsed.rejected = errors,
+        _ if !errors.is_empty() => return Err(errors),
+        _ => {}
+    }
+    Ok(parsed)
+}
+
+/// Returns the annotations of a record: every non-empty field except the one at
+/// `index`, labelled with its header if it has one.
+fn annotations(record: &StringRecord, headers: Option<&StringRecord>, index: usize) -> Vec<String> {
+    record
+        .iter()
+        .enumerate()
+        .filter(|&(i, value)| i != index && !value.trim().is_empty())
+        .map(|(i, value)| {
+            let value = value.trim();
+            match headers.and_then(|headers| headers.get(i)).map(str::trim) {
+                Some(header) if !header.is_empty() => format!("{header}={value}"),
+                _ => value.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Builds the error for a column missing from the header (line 1) or from a record.
+fn missing_column(line: usize, column: &CsvColumn) -> ParseError {
+    ParseError {
+        line,
+        column: 0,
+        text: match column {
+            CsvColumn::Name(name) => name.clone(),
+            CsvColumn::Index(index) => (index + 1).to_string(),
+        },
+        kind: ParseErrorKind::MissingColumn,
+    }
+}
+
+/// Converts a failure of the CSV reader into an I/O [`ParseError`].
+fn csv_error(err: csv::Error) -> ParseError {
+    let line = err
+        .position()
+        .map_or(0, |pos| usize::try_from(pos.line()).unwrap_or(usize::MAX));
+    let kind = match err.kind() {
+        csv::ErrorKind::Io(err) => err.kind(),
+        _ => io::ErrorKind::InvalidData,
+    };
+    ParseError {
+        line,
+        column: 0,
+        text: String::new(),
+        kind: ParseErrorKind::Io(kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HostBitsMode;
+    use std::io::Cursor;
+
+    fn by_name(name: &str) -> CsvOptions {
+        CsvOptions {
+            column: CsvColumn::Name(name.to_string()),
+            has_headers: true,
+        }
+    }
+
+    #[test]
+    fn reads_the_named_column_and_annotates_with_the_others() {
+        let input = "name, subnet ,site\n\
+                     web,10.0.0.0/24,\"eu, west\"\n\
+                     db,\"10.0.1.5 - 10.0.1.6\",\n\
+                     empty,,eu\n";
+        let parsed = parse_csv_ip_nets(
+            Cursor::new(input),
+            &by_name("subnet"),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        let nets: Vec<(String, Vec<String>)> = parsed
+            .nets
+            .iter()
+            .map(|entry| (entry.net.to_string(), entry.annotations.clone()))
+            .collect();
+        assert_eq!(
+            nets,
+            vec![
+                (
+                    "10.0.0.0/24".to_string(),
+                    vec!["name=web".to_string(), "site=eu, west".to_string()]
+                ),
+                ("10.0.1.5/32".to_string(), vec!["name=db".to_string()]),
+                ("10.0.1.6/32".to_string(), vec!["name=db".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn selects_by_index_without_headers() {
+        let csv = CsvOptions {
+            column: CsvColumn::Index(1),
+            has_headers: false,
+        };
+        let parsed = parse_csv_ip_nets(
+            Cursor::new("web,10.0.0.0/24\n\"multi\nline\",2001:db8::/32\n"),
+            &csv,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(parsed.nets.len(), 2);
+        assert_eq!(parsed.nets[0].annotations, vec!["web"]);
+        assert_eq!(parsed.nets[1].annotations, vec!["multi\nline"]);
+    }
+
+    #[test]
+    fn reports_missing_columns() {
+        let errors = parse_csv_ip_nets(
+            Cursor::new("name,cidr\n"),
+            &by_name("subnet"),
+            &ParseOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "Line 1: missing CSV column \"subnet\""
+        );
+
+        let csv = CsvOptions {
+            column: CsvColumn::Index(1),
+            has_headers: false,
+        };
+        let options = ParseOptions {
+            error_mode: ErrorMode::Skip,
+            ..ParseOptions::default()
+        };
+        let parsed = parse_csv_ip_nets(Cursor::new("a,10.0.0.0/8\nb\n"), &csv, &options).unwrap();
+        assert_eq!(parsed.nets.len(), 1);
+        assert_eq!(parsed.rejected[0].line, 2);
+        assert_eq!(parsed.rejected[0].kind, ParseErrorKind::MissingColumn);
+    }
+
+    #[test]
+    fn reports_invalid_fields_with_line_and_field_text() {
+        let input = "name,subnet\nweb,10.0.0.0/24\ndb,10.0.0.0/40\n";
+        let errors = parse_csv_ip_nets(
+            Cursor::new(input),
+            &by_name("subnet"),
+            &ParseOptions::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            (errors[0].line, errors[0].column, errors[0].text.as_str()),
+            (3, 10, "10.0.0.0/40")
+        );
+        assert_eq!(errors[0].kind, ParseErrorKind::InvalidPrefix);
+
+        let options = ParseOptions {
+            host_bits: HostBitsMode::Warn,
+            ..ParseOptions::default()
+        };
+        let parsed = parse_csv_ip_nets(
+            Cursor::new("subnet\n10.0.0.1/24\n"),
+            &by_name("subnet"),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(parsed.nets[0].net.to_string(), "10.0.0.0/24");
+        assert_eq!(parsed.normalized[0].line, 2);
+    }
+}
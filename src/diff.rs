@@ -0,0 +1,105 @@
+//! Address-space comparison of two network lists.
+//!
+//! A [`NetDiff`] describes which addresses were added and removed between an old and a
+//! new list, regardless of how either list is written: splitting, merging or reordering
+//! networks produces no difference. It implements [`serde::Serialize`] so that it can be
+//! emitted as JSON for automation.
+
+use ipnet::IpNet;
+use serde::Serialize;
+
+use crate::{exclude_ip_nets, ip_address_count};
+
+/// Difference between the address space of two network lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NetDiff {
+    /// Minimal CIDRs covering the addresses in the new list but not the old one.
+    pub added: Vec<IpNet>,
+    /// Minimal CIDRs covering the addresses in the old list but not the new one.
+    pub removed: Vec<IpNet>,
+    /// Number of addresses in `added`.
+    pub added_addresses: u128,
+    /// Number of addresses in `removed`.
+    pub removed_addresses: u128,
+}
+
+impl NetDiff {
+    /// Returns whether both lists cover exactly the same addresses.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compares the addresses covered by two lists of networks.
+///
+/// Both families are compared separately. Each side of the result lists all IPv4
+/// networks first, then all IPv6 networks. Address counts saturate at `u128::MAX`.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::diff_ip_nets;
+/// use ipnet::IpNet;
+///
+/// let old: Vec<IpNet> = vec!["10.0.0.0/24".parse().unwrap(), "10.0.1.0/24".parse().unwrap()];
+/// let new: Vec<IpNet> = vec!["10.0.0.0/23".parse().unwrap(), "10.0.8.0/24".parse().unwrap()];
+/// let diff = diff_ip_nets(old, new);
+///
+/// assert_eq!(diff.added, vec!["10.0.8.0/24".parse::<IpNet>().unwrap()]);
+/// assert!(diff.removed.is_empty());
+/// ```
+pub fn diff_ip_nets(old: Vec<IpNet>, new: Vec<IpNet>) -> NetDiff {
+    let added = exclude_ip_nets(new.clone(), old.clone());
+    let removed = exclude_ip_nets(old, new);
+
+    NetDiff {
+        added_addresses: address_total(&added),
+        removed_addresses: address_total(&removed),
+        added,
+        removed,
+    }
+}
+
+fn address_total(nets: &[IpNet]) -> u128 {
+    nets.iter()
+        .map(ip_address_count)
+        .fold(0, u128::saturating_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn rewritten_lists_have_no_difference() {
+        let diff = diff_ip_nets(
+            nets(&["10.0.0.0/23", "2001:db8::/32"]),
+            nets(&[
+                "2001:db8::/33",
+                "10.0.1.0/24",
+                "2001:db8:8000::/33",
+                "10.0.0.0/24",
+            ]),
+        );
+
+        assert!(diff.is_empty());
+        assert_eq!(diff, NetDiff::default());
+    }
+
+    #[test]
+    fn reports_added_and_removed_ranges() {
+        let diff = diff_ip_nets(
+            nets(&["10.0.0.0/22", "192.168.0.0/24"]),
+            nets(&["10.0.0.0/23", "10.0.3.0/24", "172.16.0.0/12", "::/0"]),
+        );
+
+        assert_eq!(diff.added, nets(&["172.16.0.0/12", "::/0"]));
+        assert_eq!(diff.removed, nets(&["10.0.2.0/24", "192.168.0.0/24"]));
+        assert_eq!(diff.added_addresses, u128::MAX);
+        assert_eq!(diff.removed_addresses, 512);
+    }
+}
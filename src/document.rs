@@ -0,0 +1,394 @@
+//! JSON and YAML document input.
+//!
+//! Cloud providers publish their address ranges as structured documents, such as the
+//! AWS `ip-ranges.json` file with its `prefixes[].ip_prefix` entries. A
+//! [`DocumentQuery`] picks the networks out of such a document with a path selector,
+//! optionally keeping only the entries whose sibling fields (such as `region` or
+//! `service`) have given values. Documents are read from a local reader; nothing is
+//! ever fetched.
+
+use std::fmt;
+use std::io;
+
+use ipnet::IpNet;
+use serde_json::{Map, Value};
+
+use crate::parse::parse_entry;
+use crate::{HostBitsMode, ParseErrorKind};
+
+/// Syntax of a structured input document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// A JSON document.
+    Json,
+    /// A YAML document.
+    Yaml,
+}
+
+/// Selects networks in a JSON or YAML document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentQuery {
+    /// Path to the networks, written as a chain of `.field` lookups, `[]` (every
+    /// element of an array) and `[N]` (the element at 0-based position N), such as
+    /// `.prefixes[].ip_prefix`. A lone `.` selects the whole document.
+    pub selector: String,
+    /// Conditions on the fields of the object holding each selected value, as
+    /// `(field, value)` pairs.
+    ///
+    /// A value is kept if, for every distinct field, one of the conditions on that
+    /// field holds. Scalar fields are compared by their text, so `("port", "443")`
+    /// matches the number 443.
+    pub filters: Vec<(String, String)>,
+}
+
+/// Error describing why networks could not be read from a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DocumentError {
+    /// The document could not be read or is not valid JSON or YAML.
+    Syntax(String),
+    /// The selector is malformed.
+    Selector(String),
+    /// A selected value is not a valid network.
+    InvalidValue {
+        /// Location of the value in the document, such as `.prefixes[3].ip_prefix`.
+        path: String,
+        /// The value, as JSON text.
+        value: String,
+        /// What is wrong with it.
+        kind: ParseErrorKind,
+    },
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Syntax(message) => write!(f, "invalid document: {message}"),
+            DocumentError::Selector(message) => write!(f, "invalid selector: {message}"),
+            DocumentError::InvalidValue { path, value, kind } => {
+                write!(f, "{path}: {kind} ({value})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+/// One step of a selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// Look up a field of an object.
+    Field(String),
+    /// Visit every element of an array.
+    Each,
+    /// Visit one element of an array.
+    Index(usize),
+}
+
+/// A value reached by the selector, with the object holding it and its path.
+struct Selected<'a> {
+    value: &'a Value,
+    parent: Option<&'a Map<String, Value>>,
+    path: String,
+}
+
+/// Parse the networks selected by `query` from a JSON or YAML document.
+///
+/// Every selected value must be a string holding a CIDR, a bare address, a dotted
+/// mask or an address range, as accepted by [`parse_ip_nets`](crate::parse_ip_nets);
+/// host bits are cleared. Paths that do not exist in part of the document (such as an
+/// entry with `ipv6_prefix` instead of `ip_prefix`) and `null` values are skipped, so
+/// documents listing both families in different fields can be read one field at a
+/// time. Networks are returned in document order.
+///
+/// # Errors
+///
+/// Returns a [`DocumentError`] if the document cannot be parsed, the selector is
+/// malformed, or a selected value is not a valid network.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{parse_document_ip_nets, DocumentFormat, DocumentQuery};
+/// use std::io::Cursor;
+///
+/// let document = r#"{"prefixes": [
+///     {"ip_prefix": "3.5.140.0/22", "region": "ap-northeast-2", "service": "AMAZON"},
+///     {"ip_prefix": "52.94.76.0/22", "region": "us-west-2", "service": "AMAZON"}
+/// ]}"#;
+/// let query = DocumentQuery {
+///     selector: ".prefixes[].ip_prefix".to_string(),
+///     filters: vec![("region".to_string(), "us-west-2".to_string())],
+/// };
+/// let nets = parse_document_ip_nets(Cursor::new(document), DocumentFormat::Json, &query).unwrap();
+///
+/// assert_eq!(nets, vec!["52.94.76.0/22".parse().unwrap()]);
+/// ```
+pub fn parse_document_ip_nets<R: io::Read>(
+    reader: R,
+    format: DocumentFormat,
+    query: &DocumentQuery,
+) -> Result<Vec<IpNet>, DocumentError> {
+    let steps = parse_selector(&query.selector)?;
+    let document: Value =
+        match format {
+            DocumentFormat::Json => serde_json::from_reader(reader)
+                .map_err(|err| DocumentError::Syntax(err.to_string()))?,
+            DocumentFormat::Yaml => serde_yaml::from_reader(reader)
+                .map_err(|err| DocumentError::Syntax(err.to_string()))?,
+        };
+
+    let mut selected = vec![Selected {
+        value: &document,
+        parent: None,
+        path: String::new(),
+    }];
+    for step in &steps {
+        selected = selected
+            .into_iter()
+            .flat_map(|item| item.step(step))
+            .collect();
+    }
+
+    let mut nets = Vec::new();
+    for item in selected {
+        if item.value.is_null() || !matches_filters(item.parent, &query.filters) {
+            continue;
+        }
+
+        let path = if item.path.is_empty() {
+            ".".to_string()
+        } else {
+            item.path
+        };
+        let invalid = |kind| DocumentError::InvalidValue {
+            path: path.clone(),
+            value: item.value.to_string(),
+            kind,
+        };
+        let Some(text) = item.value.as_str() else {
+            return Err(invalid(ParseErrorKind::InvalidAddress));
+        };
+        let (parsed, _) =
+            parse_entry(text, HostBitsMode::Normalize).map_err(|(_, kind)| invalid(kind))?;
+        nets.extend(parsed);
+    }
+    Ok(nets)
+}
+
+impl<'a> Selected<'a> {
+    /// Applies one selector step, returning the values it reaches.
+    fn step(self, step: &Step) -> Vec<Selected<'a>> {
+        match (step, self.value) {
+            (Step::Field(name), Value::Object(object)) => object
+                .get(name)
+                .map(|value| Selected {
+                    value,
+                    parent: Some(object),
+                    path: format!("{}.{name}", self.path),
+                })
+                .into_iter()
+                .collect(),
+            (Step::Each, Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| Selected {
+                    value,
+                    parent: self.parent,
+                    path: format!("{}[{idx}]", self.path),
+                })
+                .collect(),
+            (Step::Index(idx), Value::Array(items)) => items
+                .get(*idx)
+                .map(|value| Selected {
+                    value,
+                    parent: self.parent,
+                    path: format!("{}[{idx}]", self.path),
+                })
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns whether `object` satisfies the filters: for every distinct field, at least
+/// one of the values given for it.
+fn matches_filters(object: Option<&Map<String, Value>>, filters: &[(String, String)]) -> bool {
+    filters.iter().all(|(field, _)| {
+        let actual = match object.and_then(|object| object.get(field)) {
+            Some(Value::String(actual)) => actual.clone(),
+            Some(actual @ (Value::Number(_) | Value::Bool(_))) => actual.to_string(),
+            _ => return false,
+        };
+        filters
+            .iter()
+            .any(|(other, expected)| other == field && *expected == actual)
+    })
+}
+
+/// Splits a selector such as `.prefixes[].ip_prefix` into its steps.
+fn parse_selector(selector: &str) -> Result<Vec<Step>, DocumentError> {
+    let invalid = |reason: &str| DocumentError::Selector(format!("{reason} in {selector:?}"));
+    if selector == "." {
+        return Ok(Vec::new());
+    }
+    if !selector.starts_with('.') {
+        return Err(invalid("expected a leading `.`"));
+    }
+
+    let mut steps = Vec::new();
+    let mut rest = selector;
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            if end == 0 {
+                return Err(invalid("expected a field name after `.`"));
+            }
+            steps.push(Step::Field(after_dot[..end].to_string()));
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let Some(end) = after_bracket.find(']') else {
+                return Err(invalid("unclosed `[`"));
+            };
+            let inside = after_bracket[..end].trim();
+            steps.push(if inside.is_empty() {
+                Step::Each
+            } else {
+                Step::Index(
+                    inside
+                        .parse()
+                        .map_err(|_| invalid("expected `[]` or an array index"))?,
+                )
+            });
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(invalid("expected `.` or `[`"));
+        }
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn query(selector: &str, filters: &[(&str, &str)]) -> DocumentQuery {
+        DocumentQuery {
+            selector: selector.to_string(),
+            filters: filters
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn select(document: &str, format: DocumentFormat, query: &DocumentQuery) -> Vec<String> {
+        parse_document_ip_nets(Cursor::new(document), format, query)
+            .unwrap()
+            .iter()
+            .map(|net| net.to_string())
+            .collect()
+    }
+
+    const AWS: &str = r#"{
+        "syncToken": "1700000000",
+        "prefixes": [
+            {"ip_prefix": "3.5.140.0/22", "region": "ap-northeast-2", "service": "AMAZON"},
+            {"ip_prefix": "52.94.76.0/22", "region": "us-west-2", "service": "AMAZON"},
+            {"ip_prefix": "52.94.76.0/22", "region": "us-west-2", "service": "EC2"},
+            {"ip_prefix": "15.230.39.0/24", "region": "us-east-1", "service": "EC2"}
+        ],
+        "ipv6_prefixes": [
+            {"ipv6_prefix": "2600:1f14::/35", "region": "us-west-2", "service": "EC2"}
+        ]
+    }"#;
+
+    #[test]
+    fn selects_every_matching_value() {
+        let nets = select(
+            AWS,
+            DocumentFormat::Json,
+            &query(".prefixes[].ip_prefix", &[]),
+        );
+        assert_eq!(nets.len(), 4);
+
+        let nets = select(
+            AWS,
+            DocumentFormat::Json,
+            &query(".ipv6_prefixes[0].ipv6_prefix", &[]),
+        );
+        assert_eq!(nets, ["2600:1f14::/35"]);
+    }
+
+    #[test]
+    fn filters_on_sibling_fields() {
+        let nets = select(
+            AWS,
+            DocumentFormat::Json,
+            &query(
+                ".prefixes[].ip_prefix",
+                &[
+                    ("service", "EC2"),
+                    ("region", "us-west-2"),
+                    ("region", "us-east-1"),
+                ],
+            ),
+        );
+
+        assert_eq!(nets, ["52.94.76.0/22", "15.230.39.0/24"]);
+    }
+
+    #[test]
+    fn reads_yaml_and_nested_arrays_skipping_missing_fields() {
+        let document = "values:\n\
+                        - properties:\n    region: westeurope\n    addressPrefixes:\n\
+                        \x20     - 20.38.64.0/19\n      - 2603:1020::/47\n\
+                        - properties:\n    region: eastus\n    addressPrefixes: []\n\
+                        - name: no-properties\n";
+        let nets = select(
+            document,
+            DocumentFormat::Yaml,
+            &query(
+                ".values[].properties.addressPrefixes[]",
+                &[("region", "westeurope")],
+            ),
+        );
+
+        assert_eq!(nets, ["20.38.64.0/19", "2603:1020::/47"]);
+    }
+
+    #[test]
+    fn reports_invalid_values_with_their_path() {
+        let document = r#"{"prefixes": [{"ip_prefix": "10.0.0.0/8"}, {"ip_prefix": 42}]}"#;
+        let err = parse_document_ip_nets(
+            Cursor::new(document),
+            DocumentFormat::Json,
+            &query(".prefixes[].ip_prefix", &[]),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            ".prefixes[1].ip_prefix: invalid IP address syntax (42)"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_selectors_and_documents() {
+        for selector in ["prefixes", ".prefixes[", ".prefixes[x]", ".prefixes..ip"] {
+            let err = parse_document_ip_nets(
+                Cursor::new("{}"),
+                DocumentFormat::Json,
+                &query(selector, &[]),
+            )
+            .unwrap_err();
+            assert!(matches!(err, DocumentError::Selector(_)), "{selector}");
+        }
+
+        let err = parse_document_ip_nets(Cursor::new("{"), DocumentFormat::Json, &query(".", &[]))
+            .unwrap_err();
+        assert!(matches!(err, DocumentError::Syntax(_)));
+    }
+}
@@ -0,0 +1,179 @@
+//! Extraction of IPv4 networks from free-form text.
+//!
+//! Unlike the line-per-CIDR parser, the extractor accepts any text, such as log files or
+//! HTML pages, and picks out every IPv4 address (`192.0.2.7`) and CIDR (`10.0.0.0/8`)
+//! that stands on its own. A match must start and end at a word boundary, so that
+//! dotted version strings (`v1.2.3.4`, `1.2.3.4.5`) and identifiers (`host10.0.0.1`)
+//! are not mistaken for addresses. Text that matches nothing is ignored.
+
+use std::io::BufRead;
+use std::net::Ipv4Addr;
+
+use ipnet::Ipv4Net;
+
+use crate::{ParseError, ParseErrorKind};
+
+/// Extracts every IPv4 address and CIDR embedded in the text read from `reader`.
+///
+/// Addresses become /32 host networks, and host bits of CIDRs are cleared, so the result
+/// can be passed straight to [`merge_ipv4_nets`](crate::merge_ipv4_nets). Networks are
+/// returned in the order they appear in the text, including duplicates.
+///
+/// A candidate is only matched at a word boundary: it must not be preceded by a letter,
+/// digit, `_` or `.`, and must not be followed by a letter, digit or `_`. A trailing `.`
+/// (as at the end of a sentence) is not part of the match. A `/` and a prefix length of
+/// at most 32 directly after an address make it a CIDR; any other text after the slash
+/// (such as a URL path) leaves the address as a single host.
+///
+/// # Errors
+///
+/// Returns a [`ParseError`] with [`ParseErrorKind::Io`] if the reader fails, for example
+/// on invalid UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::extract_ipv4_nets;
+/// use std::io::Cursor;
+///
+/// let log = "GET http://10.0.0.7/index.html from 192.168.1.0/24 (agent v1.2.3.4)\n";
+/// let nets = extract_ipv4_nets(Cursor::new(log)).unwrap();
+/// let printed: Vec<String> = nets.iter().map(|net| net.to_string()).collect();
+///
+/// assert_eq!(printed, ["10.0.0.7/32", "192.168.1.0/24"]);
+/// ```
+pub fn extract_ipv4_nets<R: BufRead>(reader: R) -> Result<Vec<Ipv4Net>, ParseError> {
+    let mut nets = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| ParseError {
+            line: idx + 1,
+            column: 0,
+            text: String::new(),
+            kind: ParseErrorKind::Io(err.kind()),
+        })?;
+        extract_line(&line, &mut nets);
+    }
+    Ok(nets)
+}
+
+/// Appends the networks found in `line` to `nets`.
+fn extract_line(line: &str, nets: &mut Vec<Ipv4Net>) {
+    let bytes = line.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let at_boundary = pos == 0 || !(is_word_byte(bytes[pos - 1]) || bytes[pos - 1] == b'.');
+        if at_boundary
+            && bytes[pos].is_ascii_digit()
+            && let Some((net, end)) = match_net(bytes, pos)
+        {
+            nets.push(net);
+            pos = end;
+        } else {
+            pos += 1;
+        }
+    }
+}
+
+/// Matches an address or CIDR starting at `start`, which must be a word boundary.
+///
+/// Returns the network and the position just past the match.
+fn match_net(bytes: &[u8], start: usize) -> Option<(Ipv4Net, usize)> {
+    let run_end = scan(bytes, start, |b| b.is_ascii_digit() || b == b'.');
+    if bytes.get(run_end).copied().is_some_and(is_word_byte) {
+        return None;
+    }
+
+    // A dot ending the run is punctuation, not part of the address
+    let addr_end = start + bytes[start..run_end].iter().rposition(|&b| b != b'.')? + 1;
+    let addr: Ipv4Addr = std::str::from_utf8(&bytes[start..addr_end])
+        .ok()?
+        .parse()
+        .ok()?;
+
+    if addr_end == run_end && bytes.get(run_end) == Some(&b'/') {
+        let prefix_end = scan(bytes, run_end + 1, |b| b.is_ascii_digit());
+        let followed_by_word = bytes.get(prefix_end).copied().is_some_and(is_word_byte);
+        let prefix = std::str::from_utf8(&bytes[run_end + 1..prefix_end])
+            .ok()
+            .and_then(|digits| digits.parse::<u8>().ok());
+        if !followed_by_word
+            && let Some(net) = prefix.and_then(|prefix| Ipv4Net::new(addr, prefix).ok())
+        {
+            return Some((net.trunc(), prefix_end));
+        }
+    }
+
+    Some((Ipv4Net::from(addr), addr_end))
+}
+
+/// Returns the position of the first byte at or after `start` that does not satisfy
+/// `accept`.
+fn scan(bytes: &[u8], start: usize, accept: impl Fn(u8) -> bool) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&b| !accept(b))
+        .map_or(bytes.len(), |len| start + len)
+}
+
+/// Returns whether `b` can be part of a word, so that an address next to it is not
+/// standing on its own.
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn extract(text: &str) -> Vec<String> {
+        extract_ipv4_nets(Cursor::new(text))
+            .unwrap()
+            .iter()
+            .map(|net| net.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_addresses_and_cidrs_in_text() {
+        let text = "Jan 1 sshd: Failed password from 203.0.113.9 port 22\n\
+                    <td>10.1.2.3/16</td><td>192.0.2.1</td>\n\
+                    peer=198.51.100.7:443, next hop 192.0.2.254.\n";
+
+        assert_eq!(
+            extract(text),
+            [
+                "203.0.113.9/32",
+                "10.1.0.0/16",
+                "192.0.2.1/32",
+                "198.51.100.7/32",
+                "192.0.2.254/32",
+            ]
+        );
+    }
+
+    #[test]
+    fn respects_word_boundaries() {
+        let text = "v1.2.3.4 1.2.3.4.5 host10.0.0.1 10.0.0.1abc 10.0.0.1_x 1.2.3 256.0.0.1\n";
+
+        assert!(extract(text).is_empty());
+    }
+
+    #[test]
+    fn slash_without_prefix_length_leaves_a_host() {
+        let text = "http://10.0.0.1/index.html 10.0.0.2/33 10.0.0.3/ 10.0.0.4/24x\n";
+
+        assert_eq!(
+            extract(text),
+            ["10.0.0.1/32", "10.0.0.2/32", "10.0.0.3/32", "10.0.0.4/32"]
+        );
+    }
+
+    #[test]
+    fn reports_io_failures() {
+        let err = extract_ipv4_nets(Cursor::new(b"10.0.0.1\n\xff\n".to_vec())).unwrap_err();
+
+        assert_eq!(err.line, 2);
+        assert!(matches!(err.kind, ParseErrorKind::Io(_)));
+    }
+}
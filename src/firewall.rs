@@ -0,0 +1,178 @@
+//! Firewall rule generation.
+//!
+//! These emitters turn a merged list of IPv4 networks into files that firewall tools
+//! load directly: an `iptables-restore` rule file, an nftables set definition for
+//! `nft -f`, and an `ipset restore` file. Each one returns the complete file contents,
+//! one entry per network, in the order given.
+
+use std::fmt::Write;
+
+use ipnet::Ipv4Net;
+
+/// Largest number of elements an ipset holds unless `maxelem` is raised.
+const IPSET_DEFAULT_MAXELEM: usize = 65536;
+
+/// Builds an `iptables-restore` file that appends one rule per network to `chain` of the
+/// `filter` table, matching the network as the source and jumping to `target`.
+///
+/// The file only appends rules, so it should be loaded with
+/// `iptables-restore --noflush` to keep the existing ones. A custom `chain` must already
+/// exist. `target` is copied verbatim after `-j`, so it may carry target options.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::iptables_rules;
+///
+/// let rules = iptables_rules(&["10.0.0.0/23".parse().unwrap()], "INPUT", "DROP");
+///
+/// assert_eq!(rules, "*filter\n-A INPUT -s 10.0.0.0/23 -j DROP\nCOMMIT\n");
+/// ```
+pub fn iptables_rules(nets: &[Ipv4Net], chain: &str, target: &str) -> String {
+    let mut out = String::from("*filter\n");
+    for net in nets {
+        let _ = writeln!(out, "-A {chain} -s {net} -j {target}");
+    }
+    out.push_str("COMMIT\n");
+    out
+}
+
+/// Builds an nftables script, for `nft -f`, that defines the interval set `set_name` in
+/// the `ip filter` table and fills it with the networks.
+///
+/// The set is created if needed and flushed before the elements are added, so loading
+/// the script again replaces the previous contents. Rules refer to it as `@set_name`,
+/// e.g. `ip saddr @blocklist drop`.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::nft_set;
+///
+/// let script = nft_set(&["10.0.0.0/23".parse().unwrap()], "blocklist");
+///
+/// assert!(script.contains("flags interval"));
+/// assert!(script.ends_with("add element ip filter blocklist { 10.0.0.0/23 }\n"));
+/// ```
+pub fn nft_set(nets: &[Ipv4Net], set_name: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "table ip filter {{");
+    let _ = writeln!(out, "\tset {set_name} {{");
+    let _ = writeln!(out, "\t\ttype ipv4_addr");
+    let _ = writeln!(out, "\t\tflags interval");
+    let _ = writeln!(out, "\t}}");
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(out, "flush set ip filter {set_name}");
+
+    // nft rejects an empty element list, so an empty set is only created and flushed
+    if !nets.is_empty() {
+        let elements: Vec<String> = nets.iter().map(Ipv4Net::to_string).collect();
+        let _ = writeln!(
+            out,
+            "add element ip filter {set_name} {{ {} }}",
+            elements.join(", ")
+        );
+    }
+    out
+}
+
+/// Builds an `ipset restore` file that creates the `hash:net` set `set_name` (if it does
+/// not exist yet) and adds the networks to it.
+///
+/// `hash:net` sets cannot hold a /0, so `0.0.0.0/0` is written as its two /1 halves.
+/// `maxelem` is raised when the set needs more than the default 65536 entries.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::ipset_restore;
+///
+/// let file = ipset_restore(&["10.0.0.0/23".parse().unwrap()], "blocklist");
+///
+/// assert_eq!(
+///     file,
+///     "create blocklist hash:net family inet -exist\nadd blocklist 10.0.0.0/23 -exist\n"
+/// );
+/// ```
+pub fn ipset_restore(nets: &[Ipv4Net], set_name: &str) -> String {
+    let entries: Vec<Ipv4Net> = nets
+        .iter()
+        .flat_map(|net| match net.prefix_len() {
+            0 => net.subnets(1).expect("/1 is a valid prefix").collect(),
+            _ => vec![*net],
+        })
+        .collect();
+
+    let mut out = format!("create {set_name} hash:net family inet");
+    if entries.len() > IPSET_DEFAULT_MAXELEM {
+        let _ = write!(out, " maxelem {}", entries.len());
+    }
+    out.push_str(" -exist\n");
+    for net in entries {
+        let _ = writeln!(out, "add {set_name} {net} -exist");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(list: &[&str]) -> Vec<Ipv4Net> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn iptables_appends_one_rule_per_network() {
+        let rules = iptables_rules(
+            &v4(&["10.0.0.0/23", "192.0.2.7/32"]),
+            "blocklist",
+            "LOG --log-prefix dropped",
+        );
+
+        assert_eq!(
+            rules,
+            "*filter\n\
+             -A blocklist -s 10.0.0.0/23 -j LOG --log-prefix dropped\n\
+             -A blocklist -s 192.0.2.7/32 -j LOG --log-prefix dropped\n\
+             COMMIT\n"
+        );
+    }
+
+    #[test]
+    fn nft_defines_and_fills_an_interval_set() {
+        let script = nft_set(&v4(&["10.0.0.0/23", "192.0.2.7/32"]), "blocklist");
+
+        assert_eq!(
+            script,
+            "table ip filter {\n\
+             \tset blocklist {\n\
+             \t\ttype ipv4_addr\n\
+             \t\tflags interval\n\
+             \t}\n\
+             }\n\
+             flush set ip filter blocklist\n\
+             add element ip filter blocklist { 10.0.0.0/23, 192.0.2.7/32 }\n"
+        );
+        assert!(!nft_set(&[], "blocklist").contains("add element"));
+    }
+
+    #[test]
+    fn ipset_splits_the_default_route_and_raises_maxelem() {
+        let file = ipset_restore(&v4(&["0.0.0.0/0"]), "all");
+        assert_eq!(
+            file,
+            "create all hash:net family inet -exist\n\
+             add all 0.0.0.0/1 -exist\n\
+             add all 128.0.0.0/1 -exist\n"
+        );
+
+        let hosts: Vec<Ipv4Net> = (0..=IPSET_DEFAULT_MAXELEM as u32)
+            .map(|idx| Ipv4Net::new(idx.into(), 32).unwrap())
+            .collect();
+        assert!(
+            ipset_restore(&hosts, "big")
+                .starts_with("create big hash:net family inet maxelem 65537 -exist\n")
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Cost-ordered merging.
+//!
+//! The default merge loop accepts any merge of neighbouring networks whose extra
+//! addresses fit the tolerance, in address order. The code here instead ranks every
+//! candidate merge by the number of extra addresses it adds and always applies the
+//! cheapest one next. This makes it possible to cap the total over-coverage of a run,
+//! or to merge just enough to reach a target number of networks, across both address
+//! families.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::ops::Bound;
+
+use crate::{
+    IpBlock, MergeStats, find_covering_supernet_impl, merge_nets_counted,
+    network_address_count_impl, network_covers_impl,
+};
+
+/// Merges `nets` losslessly, then applies cheapest-first merges for as long as
+/// `proceed` allows.
+///
+/// Before each merge, `proceed` is called with the current number of networks and the
+/// number of extra addresses the cheapest remaining merge would add. Returning `false`
+/// ends the run: declining the cheapest merge leaves nothing else to change. Counters
+/// for the lossless phase and for every merge applied are added to `stats`.
+pub(crate) fn merge_cheapest_first<N: IpBlock>(
+    nets: Vec<N>,
+    stats: &mut MergeStats,
+    mut proceed: impl FnMut(usize, u128) -> bool,
+) -> Vec<N> {
+    let lossless = merge_nets_counted(nets, 0, stats);
+    let mut state = CheapestFirst::new(&lossless);
+
+    while let Some((supernet, cost)) = state.pop_cheapest() {
+        if !proceed(state.current.len(), cost) {
+            break;
+        }
+        state.apply(supernet, cost, stats);
+    }
+
+    state.current.into_values().collect()
+}
+
+/// Merges the networks of two families losslessly, then applies the cheapest merge of
+/// either family until at most `max_entries` networks remain in total.
+///
+/// Merges of both families are ranked in one order by the number of extra addresses
+/// they add. Each family keeps at least one network, so the run also ends when no merge
+/// is left. Both lists must be non-empty. Counters are added to `stats_a` and `stats_b`.
+pub(crate) fn merge_cheapest_first_to_limit<A: IpBlock, B: IpBlock>(
+    a: Vec<A>,
+    b: Vec<B>,
+    max_entries: usize,
+    stats_a: &mut MergeStats,
+    stats_b: &mut MergeStats,
+) -> (Vec<A>, Vec<B>) {
+    let mut state_a = CheapestFirst::new(&merge_nets_counted(a, 0, stats_a));
+    let mut state_b = CheapestFirst::new(&merge_nets_counted(b, 0, stats_b));
+
+    // The cheapest candidate of each family; applying a merge in one family leaves the
+    // other's candidate valid
+    let mut next_a = state_a.pop_cheapest();
+    let mut next_b = state_b.pop_cheapest();
+    while state_a.current.len() + state_b.current.len() > max_entries {
+        let take_a = match (&next_a, &next_b) {
+            (Some((_, cost_a)), Some((_, cost_b))) => cost_a <= cost_b,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_a {
+            let (supernet, cost) = next_a.take().expect("candidate was checked");
+            state_a.apply(supernet, cost, stats_a);
+            next_a = state_a.pop_cheapest();
+        } else {
+            let (supernet, cost) = next_b.take().expect("candidate was checked");
+            state_b.apply(supernet, cost, stats_b);
+            next_b = state_b.pop_cheapest();
+        }
+    }
+
+    (
+        state_a.current.into_values().collect(),
+        state_b.current.into_values().collect(),
+    )
+}
+
+/// Working state of a cheapest-first merge.
+struct CheapestFirst<N> {
+    /// Current networks, non-overlapping, keyed by their first address.
+    current: BTreeMap<u128, N>,
+    /// Extra addresses each candidate supernet would add if applied now.
+    candidates: BTreeMap<N, u128>,
+    /// Candidates ordered by cost, then smallest supernet first, then by network.
+    /// Entries whose cost no longer matches `candidates` are stale and skipped when
+    /// popped.
+    queue: BinaryHeap<Reverse<(u128, u8, N)>>,
+}
+
+impl<N: IpBlock> CheapestFirst<N> {
+    /// Starts from a sorted, non-overlapping list of networks.
+    fn new(nets: &[N]) -> Self {
+        let mut state = CheapestFirst {
+            current: nets.iter().map(|net| (net.start(), *net)).collect(),
+            candidates: BTreeMap::new(),
+            queue: BinaryHeap::new(),
+        };
+        for pair in nets.windows(2) {
+            state.add_pair(&pair[0], &pair[1]);
+        }
+        state
+    }
+
+    /// Registers the smallest supernet covering two neighbouring networks as a candidate.
+    fn add_pair(&mut self, a: &N, b: &N) {
+        let Some(supernet) = find_covering_supernet_impl(a, b) else {
+            return;
+        };
+        let covered = self
+            .current
+            .range(supernet.start()..=supernet.end())
+            .map(|(_, net)| network_address_count_impl(net))
+            .fold(0u128, u128::saturating_add);
+        let cost = network_address_count_impl(&supernet).saturating_sub(covered);
+        self.set_cost(supernet, cost);
+    }
+
+    fn set_cost(&mut self, supernet: N, cost: u128) {
+        self.candidates.insert(supernet, cost);
+        self.queue
+            .push(Reverse((cost, N::BITS - supernet.prefix(), supernet)));
+    }
+
+    /// Removes and returns the cheapest candidate that can still be applied.
+    fn pop_cheapest(&mut self) -> Option<(N, u128)> {
+        while let Some(Reverse((cost, _, supernet))) = self.queue.pop() {
+            if self.candidates.get(&supernet) != Some(&cost) {
+                continue;
+            }
+            if self.is_covered(&supernet) {
+                // An earlier merge already produced this network or a supernet of it
+                self.candidates.remove(&supernet);
+                continue;
+            }
+            return Some((supernet, cost));
+        }
+        None
+    }
+
+    /// Returns whether a current network covers `net`.
+    fn is_covered(&self, net: &N) -> bool {
+        self.current
+            .range(..=net.start())
+            .next_back()
+            .is_some_and(|(_, candidate)| network_covers_impl(candidate, net))
+    }
+
+    /// Replaces every current network inside `supernet` with `supernet`.
+    fn apply(&mut self, supernet: N, cost: u128, stats: &mut MergeStats) {
+        self.candidates.remove(&supernet);
+
+        let inside: Vec<u128> = self
+            .current
+            .range(supernet.start()..=supernet.end())
+            .map(|(&start, _)| start)
+            .collect();
+        for start in &inside {
+            self.current.remove(start);
+        }
+        stats.tolerance_merges += 1;
+        stats.covered_removed += inside.len().saturating_sub(2);
+
+        // Every larger candidate containing this supernet now has `cost` fewer
+        // uncovered addresses; no other candidate is affected.
+        for prefix in (0..supernet.prefix()).rev() {
+            let ancestor = truncate(&supernet, prefix);
+            if let Some(&ancestor_cost) = self.candidates.get(&ancestor) {
+                self.set_cost(ancestor, ancestor_cost.saturating_sub(cost));
+            }
+        }
+
+        // Keep the set lossless: the new network may complete its sibling's supernet
+        let mut net = supernet;
+        while net.prefix() > 0 {
+            let sibling = sibling(&net);
+            if self.current.get(&sibling.start()) != Some(&sibling) {
+                break;
+            }
+            self.current.remove(&sibling.start());
+            net = truncate(&net, net.prefix() - 1);
+            stats.exact_merges += 1;
+        }
+        self.current.insert(net.start(), net);
+
+        let before = self
+            .current
+            .range(..net.start())
+            .next_back()
+            .map(|(_, n)| *n);
+        let after = self
+            .current
+            .range((Bound::Excluded(net.start()), Bound::Unbounded))
+            .next()
+            .map(|(_, n)| *n);
+        if let Some(before) = before {
+            self.add_pair(&before, &net);
+        }
+        if let Some(after) = after {
+            self.add_pair(&net, &after);
+        }
+    }
+}
+
+/// Returns the supernet of `net` with the given (shorter or equal) prefix length.
+fn truncate<N: IpBlock>(net: &N, prefix: u8) -> N {
+    let host_mask = u128::MAX
+        .checked_shr(u32::from(prefix) + 128 - u32::from(N::BITS))
+        .unwrap_or(0);
+    N::from_start(net.start() & !host_mask, prefix).expect("prefix is within range")
+}
+
+/// Returns the other half of the supernet one bit shorter than `net`.
+///
+/// `net` must have a prefix length of at least 1.
+fn sibling<N: IpBlock>(net: &N) -> N {
+    let bit = 1u128 << (N::BITS - net.prefix());
+    N::from_start(net.start() ^ bit, net.prefix()).expect("prefix is within range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipnet::{Ipv4Net, Ipv6Net};
+
+    fn v4(list: &[&str]) -> Vec<Ipv4Net> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn run(nets: Vec<Ipv4Net>, budget: u128) -> (Vec<Ipv4Net>, MergeStats) {
+        let mut stats = MergeStats::default();
+        let mut remaining = budget;
+        let merged = merge_cheapest_first(nets, &mut stats, |_, cost| {
+            let fits = cost <= remaining;
+            if fits {
+                remaining -= cost;
+            }
+            fits
+        });
+        (merged, stats)
+    }
+
+    #[test]
+    fn cheapest_merge_is_applied_first() {
+        // Merging the first pair costs 512 addresses, the second only 128
+        let nets = v4(&["10.0.0.0/24", "10.0.2.0/24", "10.1.0.0/25", "10.1.1.0/24"]);
+
+        let (merged, stats) = run(nets, 600);
+
+        assert_eq!(merged, v4(&["10.0.0.0/24", "10.0.2.0/24", "10.1.0.0/23"]));
+        assert_eq!(stats.tolerance_merges, 1);
+    }
+
+    #[test]
+    fn merged_network_joins_its_sibling_losslessly() {
+        // 10.0.0.0/25 + 10.0.1.0/24 -> 10.0.0.0/23, which completes 10.0.0.0/22
+        let nets = v4(&["10.0.0.0/25", "10.0.1.0/24", "10.0.2.0/23"]);
+
+        let (merged, stats) = run(nets, 128);
+
+        assert_eq!(merged, v4(&["10.0.0.0/22"]));
+        assert_eq!(stats.tolerance_merges, 1);
+        assert_eq!(stats.exact_merges, 1);
+    }
+
+    #[test]
+    fn larger_candidates_get_cheaper_after_a_nested_merge() {
+        // The /22 costs 512 before and 256 after the /23 merge on its right
+        let nets = v4(&["10.0.0.0/24", "10.0.2.0/25", "10.0.3.0/25"]);
+
+        let (merged, _) = run(nets, 256 + 256);
+
+        assert_eq!(merged, v4(&["10.0.0.0/22"]));
+    }
+
+    #[test]
+    fn proceed_sees_current_length_and_stops_the_run() {
+        let nets = v4(&["10.0.0.0/24", "10.0.2.0/24", "10.0.8.0/24", "10.0.16.0/24"]);
+        let mut stats = MergeStats::default();
+
+        let merged = merge_cheapest_first(nets, &mut stats, |len, _| len > 2);
+
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn truncate_and_sibling_handle_family_edges() {
+        let net: Ipv6Net = "2001:db8::/32".parse().unwrap();
+        assert_eq!(truncate(&net, 0), "::/0".parse().unwrap());
+        assert_eq!(truncate(&net, 16), "2001::/16".parse().unwrap());
+        assert_eq!(sibling(&net), "2001:db9::/32".parse().unwrap());
+
+        let host: Ipv4Net = "10.0.0.1/32".parse().unwrap();
+        assert_eq!(truncate(&host, 32), host);
+        assert_eq!(sibling(&host), "10.0.0.0/32".parse().unwrap());
+    }
+}
@@ -0,0 +1,144 @@
+//! Addressing details of a single network.
+//!
+//! A [`NetworkInfo`] lists the values usually looked up with a subnet calculator: the
+//! network and broadcast addresses, the usable host range, the netmask and wildcard
+//! mask, and the address counts. It implements [`serde::Serialize`] so that it can be
+//! emitted as JSON.
+
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+use serde::Serialize;
+
+use crate::ip_address_count;
+
+/// Addressing details of a network.
+///
+/// IPv4 networks up to /30 reserve their first address (network) and last address
+/// (broadcast), so only the addresses in between are usable. /31 networks are
+/// point-to-point links where both addresses are usable (RFC 3021), and /32 networks
+/// are a single host. IPv6 has no broadcast address, so every address is usable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkInfo {
+    /// The network in CIDR notation.
+    pub network: IpNet,
+    /// First address of the network.
+    pub network_address: IpAddr,
+    /// Last address of an IPv4 network; `None` for IPv6.
+    pub broadcast: Option<IpAddr>,
+    /// First address that can be assigned to a host.
+    pub first_usable: IpAddr,
+    /// Last address that can be assigned to a host.
+    pub last_usable: IpAddr,
+    /// Netmask, e.g. `255.255.255.0` for a /24.
+    pub netmask: IpAddr,
+    /// Wildcard (host) mask, e.g. `0.0.0.255` for a /24.
+    pub wildcard: IpAddr,
+    /// Number of addresses in the network, saturating at `u128::MAX` for `::/0`.
+    pub address_count: u128,
+    /// Number of addresses that can be assigned to hosts.
+    pub usable_count: u128,
+}
+
+impl NetworkInfo {
+    /// Describes `net`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use clpsr::NetworkInfo;
+    ///
+    /// let info = NetworkInfo::new(&"192.168.1.0/24".parse().unwrap());
+    ///
+    /// assert_eq!(info.broadcast.unwrap().to_string(), "192.168.1.255");
+    /// assert_eq!(info.first_usable.to_string(), "192.168.1.1");
+    /// assert_eq!(info.last_usable.to_string(), "192.168.1.254");
+    /// assert_eq!(info.wildcard.to_string(), "0.0.0.255");
+    /// assert_eq!(info.usable_count, 254);
+    /// ```
+    pub fn new(net: &IpNet) -> Self {
+        let address_count = ip_address_count(net);
+        let (broadcast, first_usable, last_usable, usable_count) = match net {
+            IpNet::V4(v4) if v4.prefix_len() <= 30 => {
+                let first = u32::from(v4.network()) + 1;
+                let last = u32::from(v4.broadcast()) - 1;
+                (
+                    Some(net.broadcast()),
+                    IpAddr::from(first.to_be_bytes()),
+                    IpAddr::from(last.to_be_bytes()),
+                    address_count - 2,
+                )
+            }
+            IpNet::V4(_) => (
+                Some(net.broadcast()),
+                net.network(),
+                net.broadcast(),
+                address_count,
+            ),
+            IpNet::V6(_) => (None, net.network(), net.broadcast(), address_count),
+        };
+
+        NetworkInfo {
+            network: *net,
+            network_address: net.network(),
+            broadcast,
+            first_usable,
+            last_usable,
+            netmask: net.netmask(),
+            wildcard: net.hostmask(),
+            address_count,
+            usable_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(net: &str) -> NetworkInfo {
+        NetworkInfo::new(&net.parse().unwrap())
+    }
+
+    fn addr(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn ipv4_reserves_network_and_broadcast() {
+        let info = info("10.0.0.0/23");
+
+        assert_eq!(info.network_address, addr("10.0.0.0"));
+        assert_eq!(info.broadcast, Some(addr("10.0.1.255")));
+        assert_eq!(info.first_usable, addr("10.0.0.1"));
+        assert_eq!(info.last_usable, addr("10.0.1.254"));
+        assert_eq!(info.netmask, addr("255.255.254.0"));
+        assert_eq!(info.wildcard, addr("0.0.1.255"));
+        assert_eq!(info.address_count, 512);
+        assert_eq!(info.usable_count, 510);
+    }
+
+    #[test]
+    fn point_to_point_and_host_networks_are_fully_usable() {
+        let link = info("10.0.0.4/31");
+        assert_eq!(link.first_usable, addr("10.0.0.4"));
+        assert_eq!(link.last_usable, addr("10.0.0.5"));
+        assert_eq!(link.usable_count, 2);
+
+        let host = info("10.0.0.7/32");
+        assert_eq!(host.broadcast, Some(addr("10.0.0.7")));
+        assert_eq!(host.first_usable, host.last_usable);
+        assert_eq!(host.usable_count, 1);
+    }
+
+    #[test]
+    fn ipv6_has_no_broadcast() {
+        let info = info("2001:db8::/64");
+
+        assert_eq!(info.broadcast, None);
+        assert_eq!(info.first_usable, addr("2001:db8::"));
+        assert_eq!(info.last_usable, addr("2001:db8::ffff:ffff:ffff:ffff"));
+        assert_eq!(info.netmask, addr("ffff:ffff:ffff:ffff::"));
+        assert_eq!(info.usable_count, 1 << 64);
+    }
+}
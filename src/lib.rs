@@ -1,8 +1,9 @@
 //! CIDR merge utility library.
 //!
-//! This library provides functionality to parse, normalize, and merge IPv4 CIDR blocks
-//! into a minimal covering set. It supports both lossless (exact) merging and merging
-//! with tolerance for extra addresses.
+//! This library provides functionality to parse, normalize, and merge IPv4 and IPv6
+//! CIDR blocks into a minimal covering set. It supports both lossless (exact) merging
+//! and merging with tolerance for extra addresses. The two address families are always
+//! merged independently of each other.
 //!
 //! # Examples
 //!
@@ -19,59 +20,674 @@
 //! assert_eq!(merged[0].to_string(), "10.0.0.0/22");
 //! ```
 
-use std::io::BufRead;
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+
+mod csv_input;
+mod diff;
+mod document;
+mod extract;
+mod firewall;
+mod greedy;
+mod info;
+mod optimal;
+mod parse;
+mod prefix_list;
+mod provenance;
+mod report;
+mod setops;
+mod split;
+mod stats;
+
+pub use csv_input::{CsvColumn, CsvOptions, parse_csv_ip_nets};
+pub use diff::{NetDiff, diff_ip_nets};
+pub use document::{DocumentError, DocumentFormat, DocumentQuery, parse_document_ip_nets};
+pub use extract::extract_ipv4_nets;
+pub use firewall::{ipset_restore, iptables_rules, nft_set};
+pub use info::NetworkInfo;
+pub use parse::{
+    ErrorMode, HostBitsMode, ParseError, ParseErrorKind, ParseOptions, ParsedInput, ParsedRecords,
+    parse_ip_nets, parse_ip_nets_with_options, parse_ipv4_nets,
+};
+pub use prefix_list::{LengthBound, PrefixListOptions, RouterSyntax, prefix_list};
+pub use provenance::{MergeKind, MergedNet, merge_with_provenance};
+pub use report::{MergeReport, NetworkReport};
+pub use setops::{
+    complement_ip_nets, complement_ipv4_nets, complement_ipv6_nets, exclude_ip_nets,
+    exclude_ipv4_nets, exclude_ipv6_nets, intersect_ip_nets, intersect_ipv4_nets,
+    intersect_ipv6_nets, ip_nets_to_ranges, union_ip_nets, union_ipv4_nets, union_ipv6_nets,
+};
+pub use split::{split_count, split_ip_nets, split_ipv4_nets, split_ipv6_nets};
+pub use stats::MergeStats;
 
-use ipnet::Ipv4Net;
+/// Normalize, deduplicate, and merge IPv4 CIDRs into a minimal covering set.
+///
+/// This function merges adjacent networks with identical prefix lengths when
+/// their combined supernet exactly represents the two subnets. When `tolerance`
+/// is greater than 0, it may also merge networks that introduce extra addresses
+/// as long as the added address count does not exceed the tolerance.
+///
+/// # Arguments
+///
+/// * `nets` - Vector of IPv4 networks to merge
+/// * `tolerance` - Maximum number of extra addresses allowed when merging (0 for lossless merging only)
+pub fn merge_ipv4_nets(nets: Vec<Ipv4Net>, tolerance: u64) -> Vec<Ipv4Net> {
+    merge_nets(nets, u128::from(tolerance))
+}
 
-/// Parse IPv4 CIDRs from the provided buffered reader.
+/// Normalize, deduplicate, and merge IPv6 CIDRs into a minimal covering set.
 ///
-/// Empty lines are ignored. Invalid CIDRs return a descriptive error with the
-/// offending line number.
-pub fn parse_ipv4_nets<R: BufRead>(reader: R) -> Result<Vec<Ipv4Net>, String> {
-    let mut nets = Vec::new();
-    for (idx, line) in reader.lines().enumerate() {
-        let raw = line.map_err(|err| format!("Failed to read line {}: {err}", idx + 1))?;
-        let trimmed = raw.trim();
-        if trimmed.is_empty() {
-            continue;
+/// Behaves like [`merge_ipv4_nets`], but with 128-bit address arithmetic. The
+/// tolerance is a `u128` because a single IPv6 prefix can span far more than
+/// `u64::MAX` addresses.
+///
+/// # Arguments
+///
+/// * `nets` - Vector of IPv6 networks to merge
+/// * `tolerance` - Maximum number of extra addresses allowed when merging (0 for lossless merging only)
+pub fn merge_ipv6_nets(nets: Vec<Ipv6Net>, tolerance: u128) -> Vec<Ipv6Net> {
+    merge_nets(nets, tolerance)
+}
+
+/// Normalize, deduplicate, and merge a dual-stack list of CIDRs.
+///
+/// IPv4 and IPv6 networks are merged independently of each other, each with its
+/// own tolerance budget, since an address count means something very different in
+/// the two families. The result lists all IPv4 networks first, then all IPv6
+/// networks, each family sorted by address.
+///
+/// # Arguments
+///
+/// * `nets` - Vector of IPv4 and/or IPv6 networks to merge
+/// * `tolerance_v4` - Maximum number of extra IPv4 addresses allowed per merge
+/// * `tolerance_v6` - Maximum number of extra IPv6 addresses allowed per merge
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::merge_ip_nets;
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = ["2001:db8:1::/48", "10.0.1.0/24", "2001:db8::/48", "10.0.0.0/24"]
+///     .iter()
+///     .map(|s| s.parse().unwrap())
+///     .collect();
+/// let merged = merge_ip_nets(nets, 0, 0);
+///
+/// assert_eq!(merged[0].to_string(), "10.0.0.0/23");
+/// assert_eq!(merged[1].to_string(), "2001:db8::/47");
+/// ```
+pub fn merge_ip_nets(nets: Vec<IpNet>, tolerance_v4: u64, tolerance_v6: u128) -> Vec<IpNet> {
+    let (v4, v6) = split_by_family(nets);
+
+    merge_ipv4_nets(v4, tolerance_v4)
+        .into_iter()
+        .map(IpNet::V4)
+        .chain(merge_ipv6_nets(v6, tolerance_v6).into_iter().map(IpNet::V6))
+        .collect()
+}
+
+/// Merges a dual-stack list of CIDRs like [`merge_ip_nets`], also returning
+/// statistics about the run.
+///
+/// # Arguments
+///
+/// * `nets` - Vector of IPv4 and/or IPv6 networks to merge
+/// * `tolerance_v4` - Maximum number of extra IPv4 addresses allowed per merge
+/// * `tolerance_v6` - Maximum number of extra IPv6 addresses allowed per merge
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::merge_ip_nets_with_stats;
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = ["10.0.0.0/24", "10.0.0.0/24", "10.0.1.0/24", "10.0.1.128/25"]
+///     .iter()
+///     .map(|s| s.parse().unwrap())
+///     .collect();
+/// let (merged, stats) = merge_ip_nets_with_stats(nets, 0, 0);
+///
+/// assert_eq!(merged.len(), 1);
+/// assert_eq!(stats.duplicates_dropped, 1);
+/// assert_eq!(stats.covered_removed, 1);
+/// assert_eq!(stats.exact_merges, 1);
+/// assert_eq!(stats.addresses_before, 512);
+/// ```
+pub fn merge_ip_nets_with_stats(
+    nets: Vec<IpNet>,
+    tolerance_v4: u64,
+    tolerance_v6: u128,
+) -> (Vec<IpNet>, MergeStats) {
+    let options = MergeOptions {
+        tolerance_v4,
+        tolerance_v6,
+        ..MergeOptions::default()
+    };
+    merge_ip_nets_with_options(nets, &options)
+}
+
+/// How the tolerance limits a merge run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToleranceMode {
+    /// The tolerance caps the extra addresses of each merge on its own.
+    ///
+    /// Merges are taken in address order, and the total over-coverage of a run can
+    /// exceed the tolerance.
+    #[default]
+    PerMerge,
+    /// The tolerance caps the total extra addresses of the whole run.
+    ///
+    /// Merges are taken cheapest-first, until the cheapest remaining merge would
+    /// exceed what is left of the budget.
+    Budget,
+}
+
+/// Algorithm used to choose which networks to merge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Merge neighbouring networks as they are found ([`ToleranceMode::PerMerge`]) or
+    /// cheapest-first ([`ToleranceMode::Budget`] and entry limits).
+    ///
+    /// Fast, but the result can use more networks or more extra addresses than
+    /// necessary.
+    #[default]
+    Greedy,
+    /// Find the best possible aggregation by dynamic programming over a prefix trie.
+    ///
+    /// Within a tolerance, the result has the fewest networks possible; within an entry
+    /// limit, the fewest extra addresses possible. The tolerance is always treated as a
+    /// total budget, as with [`ToleranceMode::Budget`]. The running time grows
+    /// quadratically with the number of networks in the worst case.
+    Optimal,
+}
+
+/// Options for [`merge_ip_nets_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Maximum number of extra IPv4 addresses allowed (0 for lossless merging only).
+    pub tolerance_v4: u64,
+    /// Maximum number of extra IPv6 addresses allowed (0 for lossless merging only).
+    pub tolerance_v6: u128,
+    /// Whether the tolerances apply per merge or to the run as a whole.
+    pub tolerance_mode: ToleranceMode,
+    /// Merge until at most this many networks remain, counting both families together,
+    /// whatever the over-coverage. When set, the tolerances are ignored.
+    pub max_entries: Option<usize>,
+    /// Algorithm used to choose which networks to merge.
+    pub strategy: MergeStrategy,
+}
+
+/// Merges a dual-stack list of CIDRs with explicit [`MergeOptions`], also returning
+/// statistics about the run.
+///
+/// Each family is merged independently, with its own tolerance. With
+/// [`ToleranceMode::Budget`], the extra addresses introduced for a family never
+/// exceed its tolerance in total. [`MergeOptions::max_entries`] is the exception: it
+/// limits the networks of both families together (see [`merge_to_max_entries`]).
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{merge_ip_nets_with_options, MergeOptions, ToleranceMode};
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = ["10.0.0.0/24", "10.0.2.0/24", "10.0.4.0/24", "10.0.6.0/24"]
+///     .iter()
+///     .map(|s| s.parse().unwrap())
+///     .collect();
+/// let options = MergeOptions {
+///     tolerance_v4: 512,
+///     tolerance_mode: ToleranceMode::Budget,
+///     ..MergeOptions::default()
+/// };
+/// let (merged, stats) = merge_ip_nets_with_options(nets, &options);
+///
+/// // Each /22 merge adds 512 addresses, so the budget only covers one of them
+/// assert_eq!(merged.len(), 3);
+/// assert_eq!(stats.extra_addresses, 512);
+/// ```
+pub fn merge_ip_nets_with_options(
+    nets: Vec<IpNet>,
+    options: &MergeOptions,
+) -> (Vec<IpNet>, MergeStats) {
+    let (v4, v6) = split_by_family(nets);
+    let mut stats = MergeStats::default();
+
+    let (merged_v4, merged_v6) = match options.max_entries {
+        Some(max_entries) if !v4.is_empty() && !v6.is_empty() => {
+            merge_families_to_limit(v4, v6, max_entries, options.strategy, &mut stats)
         }
+        _ => (
+            merge_family_with_stats(
+                v4,
+                FamilyMerge::new(options, u128::from(options.tolerance_v4)),
+                &mut stats,
+            ),
+            merge_family_with_stats(
+                v6,
+                FamilyMerge::new(options, options.tolerance_v6),
+                &mut stats,
+            ),
+        ),
+    };
+
+    let merged = merged_v4
+        .into_iter()
+        .map(IpNet::V4)
+        .chain(merged_v6.into_iter().map(IpNet::V6))
+        .collect();
+    (merged, stats)
+}
 
-        match trimmed.parse::<Ipv4Net>() {
-            Ok(net) => nets.push(net),
-            Err(err) => return Err(format!("Line {}: {err}", idx + 1)),
+/// Merges a dual-stack list of CIDRs down to at most `max_entries` networks in total.
+///
+/// After a lossless merge, the merge that adds the fewest extra addresses is applied
+/// repeatedly until at most `max_entries` networks remain, whatever the over-coverage.
+/// Merges of both families are ranked together by the number of addresses they add,
+/// so the limit counts IPv4 and IPv6 networks alike. Networks of different families
+/// are never merged with each other, so each family present keeps at least one
+/// network: a limit below the number of families is treated as that number. The
+/// total over-coverage accepted is returned as [`MergeStats::extra_addresses`].
+///
+/// # Arguments
+///
+/// * `nets` - Vector of IPv4 and/or IPv6 networks to merge
+/// * `max_entries` - Maximum number of networks in the result
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::merge_to_max_entries;
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = ["10.0.0.0/24", "10.0.2.0/24", "10.0.9.0/24"]
+///     .iter()
+///     .map(|s| s.parse().unwrap())
+///     .collect();
+/// let (merged, stats) = merge_to_max_entries(nets, 2);
+///
+/// assert_eq!(merged[0].to_string(), "10.0.0.0/22");
+/// assert_eq!(merged[1].to_string(), "10.0.9.0/24");
+/// assert_eq!(stats.extra_addresses, 512);
+/// ```
+pub fn merge_to_max_entries(nets: Vec<IpNet>, max_entries: usize) -> (Vec<IpNet>, MergeStats) {
+    let options = MergeOptions {
+        max_entries: Some(max_entries),
+        ..MergeOptions::default()
+    };
+    merge_ip_nets_with_options(nets, &options)
+}
+
+/// How the networks of a single family are merged.
+#[derive(Debug, Clone, Copy)]
+enum FamilyMerge {
+    /// The merge loop, with a per-merge tolerance.
+    PerMerge(u128),
+    /// Cheapest-first merges within a total budget of extra addresses.
+    Budget(u128),
+    /// Cheapest-first merges until at most this many networks remain.
+    MaxEntries(usize),
+    /// The optimal aggregation within a limit.
+    Optimal(optimal::Limit),
+}
+
+impl FamilyMerge {
+    /// Selects the merge for a family whose tolerance is `tolerance`.
+    fn new(options: &MergeOptions, tolerance: u128) -> Self {
+        match (
+            options.strategy,
+            options.max_entries,
+            options.tolerance_mode,
+        ) {
+            (MergeStrategy::Greedy, Some(max_entries), _) => FamilyMerge::MaxEntries(max_entries),
+            (MergeStrategy::Greedy, None, ToleranceMode::PerMerge) => {
+                FamilyMerge::PerMerge(tolerance)
+            }
+            (MergeStrategy::Greedy, None, ToleranceMode::Budget) => FamilyMerge::Budget(tolerance),
+            (MergeStrategy::Optimal, Some(max_entries), _) => {
+                FamilyMerge::Optimal(optimal::Limit::MaxEntries(max_entries))
+            }
+            (MergeStrategy::Optimal, None, _) => {
+                FamilyMerge::Optimal(optimal::Limit::Budget(tolerance))
+            }
         }
     }
+}
 
-    Ok(nets)
+/// Merges two non-empty families down to at most `max_entries` networks in total,
+/// adding the statistics of each family to `total`.
+fn merge_families_to_limit(
+    v4: Vec<Ipv4Net>,
+    v6: Vec<Ipv6Net>,
+    max_entries: usize,
+    strategy: MergeStrategy,
+    total: &mut MergeStats,
+) -> (Vec<Ipv4Net>, Vec<Ipv6Net>) {
+    let mut stats_v4 = MergeStats {
+        addresses_before: covered_address_count(&v4),
+        ..MergeStats::default()
+    };
+    let mut stats_v6 = MergeStats {
+        addresses_before: covered_address_count(&v6),
+        ..MergeStats::default()
+    };
+    let (merged_v4, merged_v6) = match strategy {
+        MergeStrategy::Greedy => {
+            greedy::merge_cheapest_first_to_limit(v4, v6, max_entries, &mut stats_v4, &mut stats_v6)
+        }
+        MergeStrategy::Optimal => {
+            optimal::merge_optimal_to_limit(v4, v6, max_entries, &mut stats_v4, &mut stats_v6)
+        }
+    };
+
+    finish_family_stats(&merged_v4, stats_v4, total);
+    finish_family_stats(&merged_v6, stats_v6, total);
+    (merged_v4, merged_v6)
 }
 
-/// Normalize, deduplicate, and merge IPv4 CIDRs into a minimal covering set.
+/// Merges the networks of one family, adding the run's statistics to `total`.
+fn merge_family_with_stats<N: IpBlock>(
+    nets: Vec<N>,
+    strategy: FamilyMerge,
+    total: &mut MergeStats,
+) -> Vec<N> {
+    if nets.is_empty() {
+        return nets;
+    }
+
+    let mut stats = MergeStats {
+        addresses_before: covered_address_count(&nets),
+        ..MergeStats::default()
+    };
+    let merged = match strategy {
+        FamilyMerge::PerMerge(tolerance) => merge_nets_counted(nets, tolerance, &mut stats),
+        FamilyMerge::Budget(budget) => {
+            let mut remaining = budget;
+            greedy::merge_cheapest_first(nets, &mut stats, |_, cost| {
+                let fits = cost <= remaining;
+                if fits {
+                    remaining -= cost;
+                }
+                fits
+            })
+        }
+        FamilyMerge::MaxEntries(max_entries) => {
+            greedy::merge_cheapest_first(nets, &mut stats, |len, _| len > max_entries)
+        }
+        FamilyMerge::Optimal(limit) => optimal::merge_optimal(nets, limit, &mut stats),
+    };
+
+    finish_family_stats(&merged, stats, total);
+    merged
+}
+
+/// Completes the statistics of one family from its merged networks and adds them to
+/// `total`.
+fn finish_family_stats<N: IpBlock>(merged: &[N], mut stats: MergeStats, total: &mut MergeStats) {
+    stats.output_count = merged.len();
+    stats.addresses_after = merged
+        .iter()
+        .map(network_address_count_impl)
+        .fold(0, u128::saturating_add);
+    stats.extra_addresses = stats.addresses_after.saturating_sub(stats.addresses_before);
+    total.add(&stats);
+}
+
+/// Returns the number of distinct addresses covered by `nets`, which may overlap.
+fn covered_address_count<N: IpBlock>(nets: &[N]) -> u128 {
+    let mut ranges: Vec<(u128, u128)> = nets.iter().map(|net| (net.start(), net.end())).collect();
+    ranges.sort_unstable();
+
+    let mut total: u128 = 0;
+    let mut covered_end: Option<u128> = None;
+    for (start, end) in ranges {
+        let start = match covered_end {
+            Some(covered) if end <= covered => continue,
+            Some(covered) if start <= covered => covered + 1,
+            _ => start,
+        };
+        total = total.saturating_add((end - start).saturating_add(1));
+        covered_end = Some(end);
+    }
+    total
+}
+
+/// A network together with the free-form labels attached to it and the names of the
+/// inputs it was read from.
+///
+/// When parsing, the labels come from the trailing comment of the input line, and the
+/// sources are left empty for the caller to fill in (e.g. with a file name). After
+/// [`merge_annotated_ip_nets`], both are the combined values of every input network
+/// the merged network absorbed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedNet {
+    /// The network.
+    pub net: IpNet,
+    /// Labels attached to the network, in input order and without duplicates.
+    pub annotations: Vec<String>,
+    /// Names of the inputs (such as files) the network came from, in input order and
+    /// without duplicates.
+    pub sources: Vec<String>,
+}
+
+impl From<IpNet> for AnnotatedNet {
+    fn from(net: IpNet) -> Self {
+        AnnotatedNet {
+            net,
+            annotations: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+/// Merges annotated networks, carrying annotations through to the merged output.
 ///
-/// This function merges adjacent networks with identical prefix lengths when
-/// their combined supernet exactly represents the two subnets. When `tolerance`
-/// is greater than 0, it may also merge networks that introduce extra addresses
-/// as long as the added address count does not exceed the tolerance.
+/// The networks are merged exactly like [`merge_ip_nets`]. Each merged network is
+/// labelled with the annotations and sources of every input network it covers, in
+/// input order, with repeated values listed once.
 ///
 /// # Arguments
 ///
-/// * `nets` - Vector of IPv4 networks to merge
-/// * `tolerance` - Maximum number of extra addresses allowed when merging (0 for lossless merging only)
-pub fn merge_ipv4_nets(nets: Vec<Ipv4Net>, tolerance: u64) -> Vec<Ipv4Net> {
+/// * `nets` - Annotated IPv4 and/or IPv6 networks to merge
+/// * `tolerance_v4` - Maximum number of extra IPv4 addresses allowed per merge
+/// * `tolerance_v6` - Maximum number of extra IPv6 addresses allowed per merge
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{merge_annotated_ip_nets, parse_ip_nets_with_options, ParseOptions};
+/// use std::io::Cursor;
+///
+/// let input = Cursor::new("10.0.0.0/24 # prod-eu\n10.0.1.0/24 # prod-us\n");
+/// let parsed = parse_ip_nets_with_options(input, &ParseOptions::default()).unwrap();
+/// let merged = merge_annotated_ip_nets(parsed.into_annotated(), 0, 0);
+///
+/// assert_eq!(merged[0].net.to_string(), "10.0.0.0/23");
+/// assert_eq!(merged[0].annotations, vec!["prod-eu", "prod-us"]);
+/// ```
+pub fn merge_annotated_ip_nets(
+    nets: Vec<AnnotatedNet>,
+    tolerance_v4: u64,
+    tolerance_v6: u128,
+) -> Vec<AnnotatedNet> {
+    let inputs: Vec<IpNet> = nets.iter().map(|entry| entry.net).collect();
+    let merged = merge_ip_nets(inputs.clone(), tolerance_v4, tolerance_v6);
+
+    let mut annotated: Vec<AnnotatedNet> = merged.iter().map(|&net| net.into()).collect();
+
+    for (output, indices) in annotated.iter_mut().zip(attribute_inputs(&inputs, &merged)) {
+        output.annotations = unique_values(indices.iter().map(|&idx| &nets[idx].annotations));
+        output.sources = unique_values(indices.iter().map(|&idx| &nets[idx].sources));
+    }
+
+    annotated
+}
+
+/// Concatenates `lists`, keeping only the first occurrence of each value.
+fn unique_values<'a>(lists: impl IntoIterator<Item = &'a Vec<String>>) -> Vec<String> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    lists
+        .into_iter()
+        .flatten()
+        .filter(|value| seen.insert(value.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Groups the indices of `inputs` by the merged networks that overlap each of them.
+///
+/// Returns one list per network in `merged`, holding the indices of the inputs it
+/// overlaps in ascending order. `merged` must be sorted and non-overlapping. After a
+/// merge each input lies inside exactly one merged network; after an exclusion an
+/// input may be split across several of them, or dropped entirely.
+fn attribute_inputs(inputs: &[IpNet], merged: &[IpNet]) -> Vec<Vec<usize>> {
+    let mut attribution = vec![Vec::new(); merged.len()];
+    for (idx, net) in inputs.iter().enumerate() {
+        for overlapping in overlapping_indices(merged, net) {
+            attribution[overlapping].push(idx);
+        }
+    }
+    attribution
+}
+
+/// Returns the indices of the networks in `merged` that overlap `net`.
+///
+/// `merged` must be sorted and non-overlapping, which keeps the overlapping networks
+/// (if any) contiguous.
+fn overlapping_indices(merged: &[IpNet], net: &IpNet) -> std::ops::Range<usize> {
+    let first = merged.partition_point(|candidate| candidate.broadcast() < net.network());
+    let end = merged.partition_point(|candidate| candidate.network() <= net.broadcast());
+    first..end.max(first)
+}
+
+/// Returns the number of addresses in a network of either family.
+///
+/// See `network_address_count` for details, including saturation for `::/0`.
+fn ip_address_count(net: &IpNet) -> u128 {
+    match net {
+        IpNet::V4(net) => network_address_count_impl(net),
+        IpNet::V6(net) => network_address_count_impl(net),
+    }
+}
+
+/// Splits a dual-stack list into its IPv4 and IPv6 networks, preserving order.
+fn split_by_family(nets: Vec<IpNet>) -> (Vec<Ipv4Net>, Vec<Ipv6Net>) {
+    let mut v4 = Vec::new();
+    let mut v6 = Vec::new();
+    for net in nets {
+        match net {
+            IpNet::V4(net) => v4.push(net),
+            IpNet::V6(net) => v6.push(net),
+        }
+    }
+    (v4, v6)
+}
+
+/// Address-family abstraction used by the merge helpers.
+///
+/// Addresses are widened to `u128` so that one implementation of the merge
+/// arithmetic serves both IPv4 (32-bit) and IPv6 (128-bit) networks.
+trait IpBlock: Copy + Ord {
+    /// Number of bits in an address of this family.
+    const BITS: u8;
+
+    /// Prefix length of the network.
+    fn prefix(&self) -> u8;
+
+    /// First address of the network (the network address).
+    fn start(&self) -> u128;
+
+    /// Last address of the network (the broadcast address for IPv4).
+    fn end(&self) -> u128;
+
+    /// Builds a network from its first address and prefix length.
+    ///
+    /// Returns `None` if the prefix length is out of range for the family.
+    fn from_start(start: u128, prefix: u8) -> Option<Self>;
+}
+
+impl IpBlock for Ipv4Net {
+    const BITS: u8 = 32;
+
+    fn prefix(&self) -> u8 {
+        self.prefix_len()
+    }
+
+    fn start(&self) -> u128 {
+        u128::from(u32::from(self.network()))
+    }
+
+    fn end(&self) -> u128 {
+        u128::from(u32::from(self.broadcast()))
+    }
+
+    fn from_start(start: u128, prefix: u8) -> Option<Self> {
+        let addr = u32::try_from(start).ok()?;
+        Ipv4Net::new(Ipv4Addr::from(addr), prefix).ok()
+    }
+}
+
+impl IpBlock for Ipv6Net {
+    const BITS: u8 = 128;
+
+    fn prefix(&self) -> u8 {
+        self.prefix_len()
+    }
+
+    fn start(&self) -> u128 {
+        u128::from(self.network())
+    }
+
+    fn end(&self) -> u128 {
+        u128::from(self.broadcast())
+    }
+
+    fn from_start(start: u128, prefix: u8) -> Option<Self> {
+        Ipv6Net::new(Ipv6Addr::from(start), prefix).ok()
+    }
+}
+
+/// Family-generic implementation of the merge loop.
+///
+/// See [`merge_ipv4_nets`] for documentation.
+fn merge_nets<N: IpBlock>(nets: Vec<N>, tolerance: u128) -> Vec<N> {
+    merge_nets_counted(nets, tolerance, &mut MergeStats::default())
+}
+
+/// The merge loop, recording what it does in `stats`.
+///
+/// Fills in the counters that describe the loop itself; the address totals are left
+/// to the caller.
+fn merge_nets_counted<N: IpBlock>(nets: Vec<N>, tolerance: u128, stats: &mut MergeStats) -> Vec<N> {
+    stats.input_count = nets.len();
     let mut normalized = nets;
     sort_and_dedup(&mut normalized);
+    stats.duplicates_dropped = stats.input_count - normalized.len();
 
     let mut changed = true;
     while changed {
         changed = false;
-        let mut merged: Vec<Ipv4Net> = Vec::new();
+        stats.passes += 1;
+        let mut merged: Vec<N> = Vec::new();
         let mut idx = 0;
 
         while idx < normalized.len() {
             // Try to merge with next network
             if idx + 1 < normalized.len()
-                && let Some((supernet, _extra_addrs)) =
+                && let Some((supernet, extra_addrs)) =
                     try_merge_with_tolerance(&normalized[idx], &normalized[idx + 1], tolerance)
             {
+                if supernet == normalized[idx] || supernet == normalized[idx + 1] {
+                    // One network contained the other; nothing was actually merged
+                    stats.covered_removed += 1;
+                } else if extra_addrs == 0 {
+                    stats.exact_merges += 1;
+                } else {
+                    stats.tolerance_merges += 1;
+                }
                 merged.push(supernet);
                 changed = true;
                 idx += 2;
@@ -82,8 +698,12 @@ pub fn merge_ipv4_nets(nets: Vec<Ipv4Net>, tolerance: u64) -> Vec<Ipv4Net> {
             idx += 1;
         }
 
+        let merged_len = merged.len();
         sort_and_dedup(&mut merged);
         let (compacted, removed_subnets) = remove_covered_nets(merged);
+        // A merge can produce a network that is already present; the dedup drops it
+        // for the same reason the covered-subnet pass would.
+        stats.covered_removed += merged_len - compacted.len();
         changed |= removed_subnets;
         normalized = compacted;
     }
@@ -91,39 +711,48 @@ pub fn merge_ipv4_nets(nets: Vec<Ipv4Net>, tolerance: u64) -> Vec<Ipv4Net> {
     normalized
 }
 
-/// Sorts networks by network address (as u32), then by prefix length, and removes duplicates.
+/// Clears host bits, sorts networks by address, then by prefix length, and removes
+/// duplicates.
 ///
-/// Networks are sorted first by their network address in ascending order, then by prefix length
-/// in ascending order. After sorting, consecutive duplicate networks are removed.
+/// Every network is first reduced to its network address (so `10.0.0.1/24` becomes
+/// `10.0.0.0/24`). Networks are then sorted by network address in ascending order, then
+/// by prefix length in ascending order. After sorting, consecutive duplicate networks
+/// are removed.
 ///
 /// # Arguments
 ///
-/// * `nets` - Mutable vector of IPv4 networks to sort and deduplicate
+/// * `nets` - Mutable vector of networks to sort and deduplicate
 #[cfg(test)]
-pub(crate) fn sort_and_dedup(nets: &mut Vec<Ipv4Net>) {
-    nets.sort_by(|a, b| {
-        u32::from(a.addr())
-            .cmp(&u32::from(b.addr()))
-            .then(a.prefix_len().cmp(&b.prefix_len()))
-    });
-    nets.dedup();
+pub(crate) fn sort_and_dedup<N: IpBlock>(nets: &mut Vec<N>) {
+    sort_and_dedup_impl(nets)
 }
 
-/// Sorts networks by network address (as u32), then by prefix length, and removes duplicates.
+/// Clears host bits, sorts networks by address, then by prefix length, and removes
+/// duplicates.
 ///
-/// Networks are sorted first by their network address in ascending order, then by prefix length
-/// in ascending order. After sorting, consecutive duplicate networks are removed.
+/// Every network is first reduced to its network address (so `10.0.0.1/24` becomes
+/// `10.0.0.0/24`). Networks are then sorted by network address in ascending order, then
+/// by prefix length in ascending order. After sorting, consecutive duplicate networks
+/// are removed.
 ///
 /// # Arguments
 ///
-/// * `nets` - Mutable vector of IPv4 networks to sort and deduplicate
+/// * `nets` - Mutable vector of networks to sort and deduplicate
 #[cfg(not(test))]
-fn sort_and_dedup(nets: &mut Vec<Ipv4Net>) {
-    nets.sort_by(|a, b| {
-        u32::from(a.addr())
-            .cmp(&u32::from(b.addr()))
-            .then(a.prefix_len().cmp(&b.prefix_len()))
-    });
+fn sort_and_dedup<N: IpBlock>(nets: &mut Vec<N>) {
+    sort_and_dedup_impl(nets)
+}
+
+/// Implementation of `sort_and_dedup`.
+///
+/// See `sort_and_dedup` for documentation.
+fn sort_and_dedup_impl<N: IpBlock>(nets: &mut Vec<N>) {
+    for net in nets.iter_mut() {
+        *net = N::from_start(net.start(), net.prefix()).expect("prefix is within range");
+    }
+    // With host bits cleared, the derived ordering of the ipnet types compares the
+    // network address first, then the prefix length.
+    nets.sort();
     nets.dedup();
 }
 
@@ -134,7 +763,7 @@ fn sort_and_dedup(nets: &mut Vec<Ipv4Net>) {
 ///
 /// # Arguments
 ///
-/// * `nets` - Vector of IPv4 networks (must be sorted)
+/// * `nets` - Vector of networks (must be sorted)
 ///
 /// # Returns
 ///
@@ -142,7 +771,7 @@ fn sort_and_dedup(nets: &mut Vec<Ipv4Net>) {
 /// - The compacted vector with covered networks removed
 /// - A boolean indicating whether any networks were removed
 #[cfg(test)]
-pub(crate) fn remove_covered_nets(nets: Vec<Ipv4Net>) -> (Vec<Ipv4Net>, bool) {
+pub(crate) fn remove_covered_nets<N: IpBlock>(nets: Vec<N>) -> (Vec<N>, bool) {
     remove_covered_nets_impl(nets)
 }
 
@@ -153,7 +782,7 @@ pub(crate) fn remove_covered_nets(nets: Vec<Ipv4Net>) -> (Vec<Ipv4Net>, bool) {
 ///
 /// # Arguments
 ///
-/// * `nets` - Vector of IPv4 networks (must be sorted)
+/// * `nets` - Vector of networks (must be sorted)
 ///
 /// # Returns
 ///
@@ -161,14 +790,14 @@ pub(crate) fn remove_covered_nets(nets: Vec<Ipv4Net>) -> (Vec<Ipv4Net>, bool) {
 /// - The compacted vector with covered networks removed
 /// - A boolean indicating whether any networks were removed
 #[cfg(not(test))]
-fn remove_covered_nets(nets: Vec<Ipv4Net>) -> (Vec<Ipv4Net>, bool) {
+fn remove_covered_nets<N: IpBlock>(nets: Vec<N>) -> (Vec<N>, bool) {
     remove_covered_nets_impl(nets)
 }
 
 /// Implementation of `remove_covered_nets`.
 ///
 /// See `remove_covered_nets` for documentation.
-fn remove_covered_nets_impl(nets: Vec<Ipv4Net>) -> (Vec<Ipv4Net>, bool) {
+fn remove_covered_nets_impl<N: IpBlock>(nets: Vec<N>) -> (Vec<N>, bool) {
     if nets.is_empty() {
         return (nets, false);
     }
@@ -204,7 +833,7 @@ fn remove_covered_nets_impl(nets: Vec<Ipv4Net>) -> (Vec<Ipv4Net>, bool) {
 ///
 /// `true` if `supernet` covers `subnet`, `false` otherwise
 #[cfg(test)]
-pub(crate) fn network_covers(supernet: &Ipv4Net, subnet: &Ipv4Net) -> bool {
+pub(crate) fn network_covers<N: IpBlock>(supernet: &N, subnet: &N) -> bool {
     network_covers_impl(supernet, subnet)
 }
 
@@ -223,25 +852,19 @@ pub(crate) fn network_covers(supernet: &Ipv4Net, subnet: &Ipv4Net) -> bool {
 /// `true` if `supernet` covers `subnet`, `false` otherwise
 #[cfg(not(test))]
 #[allow(dead_code)]
-fn network_covers(supernet: &Ipv4Net, subnet: &Ipv4Net) -> bool {
+fn network_covers<N: IpBlock>(supernet: &N, subnet: &N) -> bool {
     network_covers_impl(supernet, subnet)
 }
 
 /// Implementation of `network_covers`.
 ///
 /// See `network_covers` for documentation.
-fn network_covers_impl(supernet: &Ipv4Net, subnet: &Ipv4Net) -> bool {
-    if supernet.prefix_len() > subnet.prefix_len() {
+fn network_covers_impl<N: IpBlock>(supernet: &N, subnet: &N) -> bool {
+    if supernet.prefix() > subnet.prefix() {
         return false;
     }
 
-    let super_start = u32::from(supernet.network());
-    let super_end = u32::from(supernet.broadcast());
-
-    let sub_start = u32::from(subnet.network());
-    let sub_end = u32::from(subnet.broadcast());
-
-    super_start <= sub_start && super_end >= sub_end
+    supernet.start() <= subnet.start() && supernet.end() >= subnet.end()
 }
 
 /// Attempts to merge two networks within the specified tolerance.
@@ -261,7 +884,7 @@ fn network_covers_impl(supernet: &Ipv4Net, subnet: &Ipv4Net) -> bool {
 ///   - `supernet`: The merged network covering both inputs
 ///   - `extra_addrs`: Number of addresses in the supernet not in the original networks
 /// * `None` if merge is not possible or would exceed tolerance
-fn try_merge_with_tolerance(a: &Ipv4Net, b: &Ipv4Net, tolerance: u64) -> Option<(Ipv4Net, u64)> {
+fn try_merge_with_tolerance<N: IpBlock>(a: &N, b: &N, tolerance: u128) -> Option<(N, u128)> {
     // First, try exact merge (lossless)
     if let Some(supernet) = try_merge_exact(a, b) {
         return Some((supernet, 0));
@@ -281,7 +904,7 @@ fn try_merge_with_tolerance(a: &Ipv4Net, b: &Ipv4Net, tolerance: u64) -> Option<
 
     // Check for overlap - if networks overlap, we need to account for that
     let overlap = network_overlap(a, b);
-    let original_total = a_addrs + b_addrs - overlap;
+    let original_total = a_addrs.saturating_add(b_addrs) - overlap;
 
     // Calculate addresses in supernet
     let supernet_addrs = network_address_count(&covering_supernet);
@@ -313,7 +936,7 @@ fn try_merge_with_tolerance(a: &Ipv4Net, b: &Ipv4Net, tolerance: u64) -> Option<
 /// * `Some(supernet)` if the networks can be merged exactly
 /// * `None` if the networks are not adjacent or have different prefix lengths
 #[cfg(test)]
-pub(crate) fn try_merge_exact(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
+pub(crate) fn try_merge_exact<N: IpBlock>(a: &N, b: &N) -> Option<N> {
     try_merge_exact_impl(a, b)
 }
 
@@ -333,38 +956,39 @@ pub(crate) fn try_merge_exact(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
 /// * `Some(supernet)` if the networks can be merged exactly
 /// * `None` if the networks are not adjacent or have different prefix lengths
 #[cfg(not(test))]
-fn try_merge_exact(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
+fn try_merge_exact<N: IpBlock>(a: &N, b: &N) -> Option<N> {
     try_merge_exact_impl(a, b)
 }
 
 /// Implementation of `try_merge_exact`.
 ///
 /// See `try_merge_exact` for documentation.
-fn try_merge_exact_impl(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
-    if a.prefix_len() != b.prefix_len() || a.prefix_len() == 0 {
+fn try_merge_exact_impl<N: IpBlock>(a: &N, b: &N) -> Option<N> {
+    if a.prefix() != b.prefix() || a.prefix() == 0 {
         return None;
     }
 
-    let prefix = a.prefix_len();
-    let block_size = 1u64 << (32 - prefix);
-    let a_net = u32::from(a.addr()) as u64;
-    let b_net = u32::from(b.addr()) as u64;
+    let prefix = a.prefix();
+    let host_bits = u32::from(N::BITS - prefix);
+    let a_net = a.start();
+    let b_net = b.start();
 
-    if !a_net.is_multiple_of(block_size * 2) {
+    // `a` must be the lower half of its parent block, i.e. the lowest network bit is clear
+    if (a_net >> host_bits) & 1 != 0 {
         return None;
     }
 
-    if a_net + block_size != b_net {
+    if a_net.checked_add(1u128 << host_bits) != Some(b_net) {
         return None;
     }
 
-    Ipv4Net::new(a.addr(), prefix - 1).ok()
+    N::from_start(a_net, prefix - 1)
 }
 
 /// Finds the minimal supernet that covers both networks.
 ///
 /// Calculates the smallest CIDR block that contains both input networks by finding
-/// the address range spanning both networks and determining the appropriate prefix length.
+/// the longest common prefix of the first and last addresses spanning both networks.
 /// The network address is aligned to the prefix boundary.
 ///
 /// # Arguments
@@ -374,17 +998,17 @@ fn try_merge_exact_impl(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
 ///
 /// # Returns
 ///
-/// * `Some(supernet)` - The minimal covering supernet (always succeeds for valid IPv4 networks)
-/// * `None` - Should not occur for valid IPv4 networks
+/// * `Some(supernet)` - The minimal covering supernet (always succeeds for valid networks)
+/// * `None` - Should not occur for valid networks
 #[cfg(test)]
-pub(crate) fn find_covering_supernet(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
+pub(crate) fn find_covering_supernet<N: IpBlock>(a: &N, b: &N) -> Option<N> {
     find_covering_supernet_impl(a, b)
 }
 
 /// Finds the minimal supernet that covers both networks.
 ///
 /// Calculates the smallest CIDR block that contains both input networks by finding
-/// the address range spanning both networks and determining the appropriate prefix length.
+/// the longest common prefix of the first and last addresses spanning both networks.
 /// The network address is aligned to the prefix boundary.
 ///
 /// # Arguments
@@ -394,81 +1018,76 @@ pub(crate) fn find_covering_supernet(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net
 ///
 /// # Returns
 ///
-/// * `Some(supernet)` - The minimal covering supernet (always succeeds for valid IPv4 networks)
-/// * `None` - Should not occur for valid IPv4 networks
+/// * `Some(supernet)` - The minimal covering supernet (always succeeds for valid networks)
+/// * `None` - Should not occur for valid networks
 #[cfg(not(test))]
-fn find_covering_supernet(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
+fn find_covering_supernet<N: IpBlock>(a: &N, b: &N) -> Option<N> {
     find_covering_supernet_impl(a, b)
 }
 
 /// Implementation of `find_covering_supernet`.
 ///
 /// See `find_covering_supernet` for documentation.
-fn find_covering_supernet_impl(a: &Ipv4Net, b: &Ipv4Net) -> Option<Ipv4Net> {
-    let a_start = u32::from(a.network());
-    let a_end = u32::from(a.broadcast());
-    let b_start = u32::from(b.network());
-    let b_end = u32::from(b.broadcast());
-
-    let min_start = a_start.min(b_start);
-    let max_end = a_end.max(b_end);
-
-    // Find the smallest prefix length (largest block) that can cover the range
-    let range_size = (max_end - min_start + 1) as u64;
-
-    // Calculate required prefix length: find largest n (smallest prefix length) where 2^(32-n) >= range_size
-    // This is equivalent to: n = floor(32 - log2(range_size))
-    // We iterate from largest to smallest to find the first (largest n) that works
-    let mut prefix_len = 32;
-    for n in (0..=32).rev() {
-        let block_size = 1u64 << (32 - n);
-        if block_size >= range_size {
-            prefix_len = n;
-            break;
-        }
-    }
+fn find_covering_supernet_impl<N: IpBlock>(a: &N, b: &N) -> Option<N> {
+    let min_start = a.start().min(b.start());
+    let max_end = a.end().max(b.end());
+
+    // The highest bit in which the first and last address differ determines how many
+    // host bits the covering block needs; every bit above it is shared network prefix.
+    let host_bits = u128::BITS - (min_start ^ max_end).leading_zeros();
+    let prefix_len = N::BITS - host_bits as u8;
 
     // Align the network address to the prefix boundary
-    let block_size = 1u64 << (32 - prefix_len);
-    let aligned_start = (min_start as u64 / block_size) * block_size;
+    let aligned_start = min_start & u128::MAX.checked_shl(host_bits).unwrap_or(0);
 
-    Ipv4Net::new(std::net::Ipv4Addr::from(aligned_start as u32), prefix_len).ok()
+    N::from_start(aligned_start, prefix_len)
 }
 
 /// Returns the number of addresses in a network.
 ///
 /// Calculates the total number of IP addresses covered by the network, including
-/// the network and broadcast addresses. For a network with prefix length `n`,
-/// this is `2^(32 - n)`.
+/// the network and broadcast addresses. For a network with prefix length `n` in an
+/// address family of `bits` bits, this is `2^(bits - n)`. The count for `::/0`
+/// (2^128) does not fit in a `u128` and saturates to `u128::MAX`.
 ///
 /// # Arguments
 ///
-/// * `net` - The IPv4 network
+/// * `net` - The network
 ///
 /// # Returns
 ///
 /// The number of addresses in the network (e.g., `/24` = 256, `/16` = 65536)
 #[cfg(test)]
-pub(crate) fn network_address_count(net: &Ipv4Net) -> u64 {
-    1u64 << (32 - net.prefix_len())
+pub(crate) fn network_address_count<N: IpBlock>(net: &N) -> u128 {
+    network_address_count_impl(net)
 }
 
 /// Returns the number of addresses in a network.
 ///
 /// Calculates the total number of IP addresses covered by the network, including
-/// the network and broadcast addresses. For a network with prefix length `n`,
-/// this is `2^(32 - n)`.
+/// the network and broadcast addresses. For a network with prefix length `n` in an
+/// address family of `bits` bits, this is `2^(bits - n)`. The count for `::/0`
+/// (2^128) does not fit in a `u128` and saturates to `u128::MAX`.
 ///
 /// # Arguments
 ///
-/// * `net` - The IPv4 network
+/// * `net` - The network
 ///
 /// # Returns
 ///
 /// The number of addresses in the network (e.g., `/24` = 256, `/16` = 65536)
 #[cfg(not(test))]
-fn network_address_count(net: &Ipv4Net) -> u64 {
-    1u64 << (32 - net.prefix_len())
+fn network_address_count<N: IpBlock>(net: &N) -> u128 {
+    network_address_count_impl(net)
+}
+
+/// Implementation of `network_address_count`.
+///
+/// See `network_address_count` for documentation.
+fn network_address_count_impl<N: IpBlock>(net: &N) -> u128 {
+    1u128
+        .checked_shl(u32::from(N::BITS - net.prefix()))
+        .unwrap_or(u128::MAX)
 }
 
 /// Calculates the number of overlapping addresses between two networks.
@@ -485,7 +1104,7 @@ fn network_address_count(net: &Ipv4Net) -> u64 {
 ///
 /// The number of addresses that overlap between the two networks
 #[cfg(test)]
-pub(crate) fn network_overlap(a: &Ipv4Net, b: &Ipv4Net) -> u64 {
+pub(crate) fn network_overlap<N: IpBlock>(a: &N, b: &N) -> u128 {
     network_overlap_impl(a, b)
 }
 
@@ -503,24 +1122,19 @@ pub(crate) fn network_overlap(a: &Ipv4Net, b: &Ipv4Net) -> u64 {
 ///
 /// The number of addresses that overlap between the two networks
 #[cfg(not(test))]
-fn network_overlap(a: &Ipv4Net, b: &Ipv4Net) -> u64 {
+fn network_overlap<N: IpBlock>(a: &N, b: &N) -> u128 {
     network_overlap_impl(a, b)
 }
 
 /// Implementation of `network_overlap`.
 ///
 /// See `network_overlap` for documentation.
-fn network_overlap_impl(a: &Ipv4Net, b: &Ipv4Net) -> u64 {
-    let a_start = u32::from(a.network());
-    let a_end = u32::from(a.broadcast());
-    let b_start = u32::from(b.network());
-    let b_end = u32::from(b.broadcast());
-
-    let overlap_start = a_start.max(b_start);
-    let overlap_end = a_end.min(b_end);
+fn network_overlap_impl<N: IpBlock>(a: &N, b: &N) -> u128 {
+    let overlap_start = a.start().max(b.start());
+    let overlap_end = a.end().min(b.end());
 
     if overlap_start <= overlap_end {
-        (overlap_end - overlap_start + 1) as u64
+        (overlap_end - overlap_start).saturating_add(1)
     } else {
         0
     }
@@ -529,7 +1143,7 @@ fn network_overlap_impl(a: &Ipv4Net, b: &Ipv4Net) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ipnet::Ipv4Net;
+    use ipnet::{IpNet, Ipv4Net, Ipv6Net};
     use std::io::Cursor;
 
     // ========== parse_ipv4_nets tests ==========
@@ -573,7 +1187,7 @@ mod tests {
         let result = parse_ipv4_nets(reader);
 
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Line 2"));
+        assert!(result.unwrap_err().to_string().contains("Line 2"));
     }
 
     #[test]
@@ -601,7 +1215,7 @@ mod tests {
         let result = parse_ipv4_nets(reader);
 
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Line 2"));
+        assert!(result.unwrap_err().to_string().contains("Line 2"));
     }
 
     #[test]
@@ -611,7 +1225,46 @@ mod tests {
         let result = parse_ipv4_nets(reader);
 
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Line 2"));
+        assert!(result.unwrap_err().to_string().contains("Line 2"));
+    }
+
+    #[test]
+    fn parse_ipv4_nets_rejects_ipv6() {
+        let input = "10.0.0.0/24\n2001:db8::/32";
+        let reader = Cursor::new(input);
+        let result = parse_ipv4_nets(reader);
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Line 2"));
+    }
+
+    // ========== parse_ip_nets tests ==========
+
+    #[test]
+    fn parse_ip_nets_detects_family_per_line() {
+        let input = "10.0.0.0/24\n2001:db8::/32\n  fe80::/10  \n\n192.168.0.0/16";
+        let reader = Cursor::new(input);
+        let result = parse_ip_nets(reader).unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                "10.0.0.0/24".parse::<IpNet>().unwrap(),
+                "2001:db8::/32".parse::<IpNet>().unwrap(),
+                "fe80::/10".parse::<IpNet>().unwrap(),
+                "192.168.0.0/16".parse::<IpNet>().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_ip_nets_returns_error_for_invalid_cidr() {
+        let input = "2001:db8::/32\n2001:db8::/129";
+        let reader = Cursor::new(input);
+        let result = parse_ip_nets(reader);
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Line 2"));
     }
 
     // ========== merge_ipv4_nets tests ==========
@@ -898,6 +1551,553 @@ mod tests {
         assert_eq!(merged[0], "0.0.0.0/0".parse::<Ipv4Net>().unwrap());
     }
 
+    #[test]
+    fn tolerance_merge_covers_both_networks_across_alignment_boundary() {
+        // 10.0.1.0/24 and 10.0.2.0/24 span 512 addresses, but no aligned /23 contains
+        // both, so the covering supernet has to be the /22.
+        let nets = vec![
+            "10.0.1.0/24".parse::<Ipv4Net>().unwrap(),
+            "10.0.2.0/24".parse::<Ipv4Net>().unwrap(),
+        ];
+
+        assert_eq!(merge_ipv4_nets(nets.clone(), 511).len(), 2);
+        assert_eq!(
+            merge_ipv4_nets(nets, 512),
+            vec!["10.0.0.0/22".parse::<Ipv4Net>().unwrap()]
+        );
+    }
+
+    // ========== merge_ipv6_nets tests ==========
+
+    #[test]
+    fn merges_adjacent_ipv6_subnets() {
+        let nets = vec![
+            "2001:db8::/64".parse::<Ipv6Net>().unwrap(),
+            "2001:db8:0:1::/64".parse::<Ipv6Net>().unwrap(),
+            "2001:db8:0:2::/63".parse::<Ipv6Net>().unwrap(),
+        ];
+
+        let merged = merge_ipv6_nets(nets, 0);
+
+        assert_eq!(merged, vec!["2001:db8::/62".parse::<Ipv6Net>().unwrap()]);
+    }
+
+    #[test]
+    fn merges_largest_adjacent_ipv6_prefixes() {
+        let nets = vec![
+            "::/1".parse::<Ipv6Net>().unwrap(),
+            "8000::/1".parse::<Ipv6Net>().unwrap(),
+        ];
+
+        let merged = merge_ipv6_nets(nets, 0);
+
+        assert_eq!(merged, vec!["::/0".parse::<Ipv6Net>().unwrap()]);
+    }
+
+    #[test]
+    fn ipv6_tolerance_uses_128_bit_counts() {
+        // Two /64s separated by a /64 gap: merging into the /62 adds 2^65 addresses,
+        // which does not fit the IPv4 tolerance type at all.
+        let nets = vec![
+            "2001:db8::/64".parse::<Ipv6Net>().unwrap(),
+            "2001:db8:0:2::/64".parse::<Ipv6Net>().unwrap(),
+        ];
+
+        assert_eq!(merge_ipv6_nets(nets.clone(), (1u128 << 65) - 1).len(), 2);
+        assert_eq!(
+            merge_ipv6_nets(nets, 1u128 << 65),
+            vec!["2001:db8::/62".parse::<Ipv6Net>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn ipv6_removes_covered_subnets() {
+        let nets = vec![
+            "2001:db8::/32".parse::<Ipv6Net>().unwrap(),
+            "2001:db8:ffff::/48".parse::<Ipv6Net>().unwrap(),
+            "2001:db8::/32".parse::<Ipv6Net>().unwrap(),
+        ];
+
+        let merged = merge_ipv6_nets(nets, 0);
+
+        assert_eq!(merged, vec!["2001:db8::/32".parse::<Ipv6Net>().unwrap()]);
+    }
+
+    // ========== merge_ip_nets tests ==========
+
+    #[test]
+    fn merge_ip_nets_merges_families_independently() {
+        let nets: Vec<IpNet> = vec![
+            "2001:db8:0:1::/64".parse().unwrap(),
+            "10.0.1.0/24".parse().unwrap(),
+            "2001:db8::/64".parse().unwrap(),
+            "10.0.0.0/24".parse().unwrap(),
+        ];
+
+        let merged = merge_ip_nets(nets, 0, 0);
+
+        assert_eq!(
+            merged,
+            vec![
+                "10.0.0.0/23".parse::<IpNet>().unwrap(),
+                "2001:db8::/63".parse::<IpNet>().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_ip_nets_applies_per_family_tolerance() {
+        let nets: Vec<IpNet> = vec![
+            "10.0.0.0/24".parse().unwrap(),
+            "10.0.2.0/24".parse().unwrap(),
+            "2001:db8::/64".parse().unwrap(),
+            "2001:db8:0:2::/64".parse().unwrap(),
+        ];
+
+        // The IPv4 budget does not leak into the IPv6 merge and vice versa
+        let v4_only = merge_ip_nets(nets.clone(), 512, 0);
+        assert_eq!(v4_only.len(), 3);
+        assert_eq!(v4_only[0], "10.0.0.0/22".parse::<IpNet>().unwrap());
+
+        let v6_only = merge_ip_nets(nets, 0, 1u128 << 65);
+        assert_eq!(v6_only.len(), 3);
+        assert_eq!(v6_only[2], "2001:db8::/62".parse::<IpNet>().unwrap());
+    }
+
+    #[test]
+    fn merge_ip_nets_never_merges_across_families() {
+        // 0.0.0.0/1 + 128.0.0.0/1 and ::/1 + 8000::/1 each collapse to /0, but stay separate
+        let nets: Vec<IpNet> = vec![
+            "8000::/1".parse().unwrap(),
+            "128.0.0.0/1".parse().unwrap(),
+            "::/1".parse().unwrap(),
+            "0.0.0.0/1".parse().unwrap(),
+        ];
+
+        let merged = merge_ip_nets(nets, u64::MAX, u128::MAX);
+
+        assert_eq!(
+            merged,
+            vec![
+                "0.0.0.0/0".parse::<IpNet>().unwrap(),
+                "::/0".parse::<IpNet>().unwrap(),
+            ]
+        );
+    }
+
+    // ========== merge_annotated_ip_nets tests ==========
+
+    fn annotated(net: &str, annotations: &[&str]) -> AnnotatedNet {
+        AnnotatedNet {
+            net: net.parse().unwrap(),
+            annotations: annotations.iter().map(|a| a.to_string()).collect(),
+            sources: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn merge_annotated_combines_labels_of_absorbed_networks() {
+        let nets = vec![
+            annotated("10.0.1.0/24", &["prod-us"]),
+            annotated("192.168.0.0/24", &["office"]),
+            annotated("10.0.0.0/24", &["prod-eu"]),
+            annotated("10.0.0.128/25", &["prod-eu"]),
+            annotated("10.0.0.0/25", &[]),
+        ];
+
+        let merged = merge_annotated_ip_nets(nets, 0, 0);
+
+        assert_eq!(
+            merged,
+            vec![
+                annotated("10.0.0.0/23", &["prod-us", "prod-eu"]),
+                annotated("192.168.0.0/24", &["office"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_annotated_follows_tolerance_merges_and_families() {
+        let nets = vec![
+            annotated("10.0.0.0/24", &["a"]),
+            annotated("10.0.2.0/24", &["b"]),
+            annotated("2001:db8::/64", &["c"]),
+            annotated("2001:db8:0:1::/64", &["d"]),
+        ];
+
+        let merged = merge_annotated_ip_nets(nets, 512, 0);
+
+        assert_eq!(
+            merged,
+            vec![
+                annotated("10.0.0.0/22", &["a", "b"]),
+                annotated("2001:db8::/63", &["c", "d"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_annotated_combines_sources() {
+        let sourced = |net: &str, source: &str| AnnotatedNet {
+            sources: vec![source.to_string()],
+            ..annotated(net, &[])
+        };
+        let nets = vec![
+            sourced("10.0.1.0/24", "b.txt"),
+            sourced("10.0.0.0/24", "a.txt"),
+            sourced("10.0.0.0/24", "b.txt"),
+        ];
+
+        let merged = merge_annotated_ip_nets(nets, 0, 0);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].sources, vec!["b.txt", "a.txt"]);
+    }
+
+    #[test]
+    fn merge_annotated_handles_empty_input() {
+        assert!(merge_annotated_ip_nets(vec![], 0, 0).is_empty());
+    }
+
+    // ========== merge_ip_nets_with_stats tests ==========
+
+    fn ip_nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn merge_stats_counts_each_step() {
+        let nets = ip_nets(&[
+            "10.0.0.0/24",
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "10.0.0.128/25",
+            "10.0.4.0/24",
+            "10.0.6.0/24",
+        ]);
+
+        let (merged, stats) = merge_ip_nets_with_stats(nets.clone(), 512, 0);
+
+        assert_eq!(merged, merge_ip_nets(nets, 512, 0));
+        assert_eq!(
+            stats,
+            MergeStats {
+                input_count: 6,
+                output_count: 1,
+                duplicates_dropped: 1,
+                covered_removed: 1,
+                exact_merges: 1,
+                tolerance_merges: 2,
+                extra_addresses: 1024,
+                passes: 4,
+                addresses_before: 1024,
+                addresses_after: 2048,
+            }
+        );
+    }
+
+    #[test]
+    fn merge_stats_sums_both_families() {
+        let nets = ip_nets(&[
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "2001:db8::/64",
+            "2001:db8::/64",
+        ]);
+
+        let (_, stats) = merge_ip_nets_with_stats(nets, 0, 0);
+
+        assert_eq!(stats.input_count, 4);
+        assert_eq!(stats.output_count, 2);
+        assert_eq!(stats.duplicates_dropped, 1);
+        assert_eq!(stats.exact_merges, 1);
+        assert_eq!(stats.addresses_before, 512 + (1u128 << 64));
+        assert_eq!(stats.addresses_after, stats.addresses_before);
+    }
+
+    #[test]
+    fn merge_stats_for_empty_input_are_zero() {
+        let (merged, stats) = merge_ip_nets_with_stats(vec![], 0, 0);
+
+        assert!(merged.is_empty());
+        assert_eq!(stats, MergeStats::default());
+    }
+
+    #[test]
+    fn budget_caps_total_extra_addresses_across_merges() {
+        let nets = ip_nets(&[
+            "10.0.0.0/24",
+            "10.0.2.0/24",
+            "10.0.4.0/24",
+            "10.0.6.0/24",
+            "2001:db8::/64",
+            "2001:db8:0:2::/64",
+        ]);
+
+        // Per merge, every /22 fits the tolerance and the total exceeds it
+        let (per_merge, per_merge_stats) = merge_ip_nets_with_stats(nets.clone(), 512, 0);
+        assert_eq!(per_merge[0], "10.0.0.0/21".parse::<IpNet>().unwrap());
+        assert!(per_merge_stats.extra_addresses > 512);
+
+        let options = MergeOptions {
+            tolerance_v4: 512,
+            tolerance_v6: 1 << 65,
+            tolerance_mode: ToleranceMode::Budget,
+            ..MergeOptions::default()
+        };
+        let (budget, budget_stats) = merge_ip_nets_with_options(nets.clone(), &options);
+        assert_eq!(
+            budget,
+            ip_nets(&["10.0.0.0/22", "10.0.4.0/24", "10.0.6.0/24", "2001:db8::/62"])
+        );
+        assert_eq!(budget_stats.extra_addresses, 512 + (1 << 65));
+        assert_eq!(budget_stats.tolerance_merges, 2);
+        for net in &nets {
+            assert!(budget.iter().any(|merged| merged.contains(net)));
+        }
+    }
+
+    #[test]
+    fn budget_of_zero_is_lossless() {
+        let nets = ip_nets(&["10.0.0.0/24", "10.0.1.0/24", "10.0.3.0/24"]);
+        let options = MergeOptions {
+            tolerance_mode: ToleranceMode::Budget,
+            ..MergeOptions::default()
+        };
+
+        let (merged, _) = merge_ip_nets_with_options(nets.clone(), &options);
+
+        assert_eq!(merged, merge_ip_nets(nets, 0, 0));
+    }
+
+    #[test]
+    fn max_entries_counts_both_families_together() {
+        let nets = ip_nets(&[
+            "10.0.0.0/24",
+            "10.0.2.0/24",
+            "10.0.8.0/24",
+            "10.0.16.0/24",
+            "2001:db8::/64",
+            "2001:db8:0:2::/64",
+            "2001:db8:1::/64",
+        ]);
+
+        // IPv4 merges add far fewer addresses, so they are all taken first
+        let (merged, stats) = merge_to_max_entries(nets.clone(), 4);
+        assert_eq!(
+            merged,
+            ip_nets(&[
+                "10.0.0.0/19",
+                "2001:db8::/64",
+                "2001:db8:0:2::/64",
+                "2001:db8:1::/64"
+            ])
+        );
+        assert_eq!(stats.output_count, 4);
+        assert_eq!(stats.extra_addresses, 8192 - 1024);
+
+        let (merged, stats) = merge_to_max_entries(nets.clone(), 3);
+        assert_eq!(
+            merged,
+            ip_nets(&["10.0.0.0/19", "2001:db8::/62", "2001:db8:1::/64"])
+        );
+        assert_eq!(stats.extra_addresses, (8192 - 1024) + (2 << 64));
+        for net in &nets {
+            assert!(merged.iter().any(|m| m.contains(net)));
+        }
+
+        // Each family keeps one network, whatever the limit
+        let (merged, _) = merge_to_max_entries(nets, 1);
+        assert_eq!(merged, ip_nets(&["10.0.0.0/19", "2001:db8::/47"]));
+    }
+
+    #[test]
+    fn optimal_max_entries_counts_both_families_together() {
+        let nets = ip_nets(&[
+            "10.0.0.0/24",
+            "10.0.2.0/24",
+            "2001:db8::/48",
+            "2001:db8:2::/48",
+        ]);
+        let options = MergeOptions {
+            max_entries: Some(3),
+            strategy: MergeStrategy::Optimal,
+            ..MergeOptions::default()
+        };
+
+        let (merged, stats) = merge_ip_nets_with_options(nets.clone(), &options);
+        assert_eq!(
+            merged,
+            ip_nets(&["10.0.0.0/22", "2001:db8::/48", "2001:db8:2::/48"])
+        );
+        assert_eq!(stats.extra_addresses, 512);
+
+        let options = MergeOptions {
+            max_entries: Some(2),
+            ..options
+        };
+        let (merged, _) = merge_ip_nets_with_options(nets, &options);
+        assert_eq!(merged, ip_nets(&["10.0.0.0/22", "2001:db8::/46"]));
+    }
+
+    #[test]
+    fn max_entries_leaves_small_inputs_lossless() {
+        let nets = ip_nets(&["10.0.0.0/24", "10.0.1.0/24", "10.0.3.0/24"]);
+
+        let (merged, stats) = merge_to_max_entries(nets.clone(), 2);
+
+        assert_eq!(merged, merge_ip_nets(nets, 0, 0));
+        assert_eq!(stats.extra_addresses, 0);
+    }
+
+    #[test]
+    fn max_entries_of_zero_merges_to_one_network() {
+        let nets = ip_nets(&["10.0.0.0/24", "192.168.0.0/24"]);
+
+        let (merged, _) = merge_to_max_entries(nets, 0);
+
+        assert_eq!(merged, ip_nets(&["0.0.0.0/0"]));
+    }
+
+    // ========== optimal strategy tests ==========
+
+    fn optimal_options(max_entries: Option<usize>, tolerance_v4: u64) -> MergeOptions {
+        MergeOptions {
+            tolerance_v4,
+            max_entries,
+            strategy: MergeStrategy::Optimal,
+            ..MergeOptions::default()
+        }
+    }
+
+    #[test]
+    fn optimal_beats_greedy_within_entry_limit() {
+        let nets = ip_nets(&[
+            "10.0.0.64/27",
+            "10.0.0.200/29",
+            "10.0.2.0/25",
+            "10.0.3.64/29",
+            "10.0.3.224/28",
+        ]);
+
+        let (greedy, greedy_stats) = merge_to_max_entries(nets.clone(), 3);
+        let (optimal, optimal_stats) =
+            merge_ip_nets_with_options(nets, &optimal_options(Some(3), 0));
+
+        assert_eq!(
+            greedy,
+            ip_nets(&["10.0.0.0/24", "10.0.2.0/25", "10.0.3.0/24"])
+        );
+        assert_eq!(greedy_stats.extra_addresses, 448);
+        assert_eq!(
+            optimal,
+            ip_nets(&["10.0.0.64/27", "10.0.0.200/29", "10.0.2.0/23"])
+        );
+        assert_eq!(optimal_stats.extra_addresses, 360);
+    }
+
+    #[test]
+    fn optimal_is_never_worse_than_greedy() {
+        // Small deterministic pseudo-random inputs inside 10.0.0.0/22
+        let mut seed: u64 = 7;
+        let mut next = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as u32
+        };
+
+        for _ in 0..300 {
+            let count = 3 + next() % 6;
+            let nets: Vec<IpNet> = (0..count)
+                .map(|_| {
+                    let addr = Ipv4Addr::from((10u32 << 24) | (next() % 1024));
+                    let prefix = 25 + (next() % 6) as u8;
+                    IpNet::V4(Ipv4Net::new(addr, prefix).unwrap().trunc())
+                })
+                .collect();
+            let lossless = merge_ip_nets(nets.clone(), 0, 0);
+
+            for max_entries in 1..=lossless.len() {
+                let (greedy, greedy_stats) = merge_to_max_entries(nets.clone(), max_entries);
+                let (optimal, optimal_stats) = merge_ip_nets_with_options(
+                    nets.clone(),
+                    &optimal_options(Some(max_entries), 0),
+                );
+
+                assert!(optimal.len() <= max_entries);
+                assert!(optimal_stats.extra_addresses <= greedy_stats.extra_addresses);
+                assert!(greedy.len() <= max_entries);
+                for net in &lossless {
+                    assert!(optimal.iter().any(|m| m.contains(net)));
+                }
+            }
+
+            for budget in [0, 64, 256, 512, 1024] {
+                let greedy_options = MergeOptions {
+                    tolerance_v4: budget,
+                    tolerance_mode: ToleranceMode::Budget,
+                    ..MergeOptions::default()
+                };
+                let (greedy, _) = merge_ip_nets_with_options(nets.clone(), &greedy_options);
+                let (per_merge, per_merge_stats) =
+                    merge_ip_nets_with_stats(nets.clone(), budget, 0);
+                let (optimal, optimal_stats) =
+                    merge_ip_nets_with_options(nets.clone(), &optimal_options(None, budget));
+
+                assert!(optimal_stats.extra_addresses <= u128::from(budget));
+                assert!(optimal.len() <= greedy.len());
+                if per_merge_stats.extra_addresses <= u128::from(budget) {
+                    assert!(optimal.len() <= per_merge.len());
+                }
+                for net in &lossless {
+                    assert!(optimal.iter().any(|m| m.contains(net)));
+                }
+                assert!(
+                    optimal
+                        .windows(2)
+                        .all(|pair| pair[0].broadcast() < pair[1].network())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn optimal_merges_each_family_independently() {
+        let nets = ip_nets(&[
+            "10.0.0.0/24",
+            "10.0.2.0/24",
+            "2001:db8::/64",
+            "2001:db8:0:2::/64",
+        ]);
+        let options = MergeOptions {
+            tolerance_v4: 512,
+            tolerance_v6: 0,
+            strategy: MergeStrategy::Optimal,
+            ..MergeOptions::default()
+        };
+
+        let (merged, stats) = merge_ip_nets_with_options(nets, &options);
+
+        assert_eq!(
+            merged,
+            ip_nets(&["10.0.0.0/22", "2001:db8::/64", "2001:db8:0:2::/64"])
+        );
+        assert_eq!(stats.extra_addresses, 512);
+    }
+
+    #[test]
+    fn covered_address_count_ignores_overlap() {
+        let nets: Vec<Ipv4Net> = ["10.0.0.0/24", "10.0.0.128/25", "10.0.1.0/25", "10.0.0.0/23"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(covered_address_count(&nets), 512);
+
+        let all: Vec<Ipv6Net> = vec!["::/0".parse().unwrap(), "::/1".parse().unwrap()];
+        assert_eq!(covered_address_count(&all), u128::MAX);
+    }
+
     // ========== Helper function tests (using internal visibility) ==========
 
     #[test]
@@ -1027,6 +2227,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_network_address_count_ipv6() {
+        assert_eq!(
+            network_address_count(&"2001:db8::1/128".parse::<Ipv6Net>().unwrap()),
+            1
+        );
+        assert_eq!(
+            network_address_count(&"2001:db8::/64".parse::<Ipv6Net>().unwrap()),
+            1u128 << 64
+        );
+        // 2^128 does not fit in a u128 and saturates
+        assert_eq!(
+            network_address_count(&"::/0".parse::<Ipv6Net>().unwrap()),
+            u128::MAX
+        );
+    }
+
+    #[test]
+    fn test_find_covering_supernet_ipv6() {
+        let a = "2001:db8::/48".parse::<Ipv6Net>().unwrap();
+        let b = "2001:db8:3::/48".parse::<Ipv6Net>().unwrap();
+        let result = find_covering_supernet(&a, &b);
+        assert_eq!(result, Some("2001:db8::/46".parse::<Ipv6Net>().unwrap()));
+    }
+
     #[test]
     fn test_network_overlap_no_overlap() {
         let a = "10.0.0.0/24".parse::<Ipv4Net>().unwrap();
@@ -1063,7 +2288,7 @@ mod tests {
 
     #[test]
     fn test_remove_covered_nets_empty() {
-        let (result, changed) = remove_covered_nets(vec![]);
+        let (result, changed) = remove_covered_nets(Vec::<Ipv4Net>::new());
         assert_eq!(result.len(), 0);
         assert!(!changed);
     }
@@ -1115,4 +2340,21 @@ mod tests {
         assert_eq!(nets[1], "10.0.0.0/24".parse::<Ipv4Net>().unwrap());
         assert_eq!(nets[2], "10.0.1.0/24".parse::<Ipv4Net>().unwrap());
     }
+
+    #[test]
+    fn test_sort_and_dedup_clears_host_bits() {
+        let mut nets = vec![
+            "10.0.0.1/24".parse::<Ipv4Net>().unwrap(),
+            "10.0.0.0/24".parse::<Ipv4Net>().unwrap(),
+            "10.0.0.255/23".parse::<Ipv4Net>().unwrap(),
+        ];
+        sort_and_dedup(&mut nets);
+        assert_eq!(
+            nets,
+            vec![
+                "10.0.0.0/23".parse::<Ipv4Net>().unwrap(),
+                "10.0.0.0/24".parse::<Ipv4Net>().unwrap(),
+            ]
+        );
+    }
 }
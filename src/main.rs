@@ -1,12 +1,22 @@
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::process;
 
-use clap::Parser;
-use ipnet::Ipv4Net;
+use clap::{Parser, Subcommand, ValueEnum};
+use ipnet::IpNet;
 
-use clpsr::{merge_ipv4_nets, parse_ipv4_nets};
+use clpsr::{
+    AnnotatedNet, CsvColumn, CsvOptions, DocumentFormat, DocumentQuery, ErrorMode, HostBitsMode,
+    LengthBound, MergeOptions, MergeReport, MergeStrategy, NetworkInfo, ParseOptions,
+    ParsedRecords, PrefixListOptions, RouterSyntax, ToleranceMode, complement_ip_nets,
+    diff_ip_nets, exclude_ip_nets, extract_ipv4_nets, intersect_ip_nets, ip_nets_to_ranges,
+    ipset_restore, iptables_rules, merge_ip_nets_with_options, nft_set, parse_csv_ip_nets,
+    parse_document_ip_nets, parse_ip_nets, parse_ip_nets_with_options, prefix_list, split_count,
+    split_ip_nets, union_ip_nets,
+};
 
 /// Parses a tolerance value from a string.
 ///
@@ -32,20 +42,43 @@ use clpsr::{merge_ipv4_nets, parse_ipv4_nets};
 /// assert_eq!(parse_tolerance("/24").unwrap(), 256);   // 2^(32-24) = 256
 /// ```
 fn parse_tolerance(s: &str) -> Result<u64, String> {
+    let tolerance = parse_tolerance_for_bits(s, 32)?;
+    u64::try_from(tolerance).map_err(|_| format!("Tolerance value too large: {s}"))
+}
+
+/// Parses an IPv6 tolerance value from a string.
+///
+/// Accepts the same two formats as [`parse_tolerance`], but bit masks are interpreted
+/// against 128-bit addresses using the formula `2^(128 - prefix_len)`.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(parse_tolerance_v6("512").unwrap(), 512);
+/// assert_eq!(parse_tolerance_v6("/64").unwrap(), 1 << 64);
+/// ```
+fn parse_tolerance_v6(s: &str) -> Result<u128, String> {
+    parse_tolerance_for_bits(s, 128)
+}
+
+/// Shared implementation of the tolerance parsers for an address family of `bits` bits.
+fn parse_tolerance_for_bits(s: &str, bits: u8) -> Result<u128, String> {
     if let Some(prefix_len_str) = s.strip_prefix('/') {
         // Parse as bit mask size (e.g., "/16")
         let prefix_len: u8 = prefix_len_str
             .parse()
             .map_err(|_| format!("Invalid prefix length: {prefix_len_str}"))?;
 
-        if prefix_len > 32 {
+        if prefix_len > bits {
             return Err(format!(
-                "Prefix length must be between 0 and 32, got: {prefix_len}"
+                "Prefix length must be between 0 and {bits}, got: {prefix_len}"
             ));
         }
 
-        // Convert prefix length to address count: 2^(32 - prefix_len)
-        let address_count = 1u64 << (32 - prefix_len);
+        // Convert prefix length to address count: 2^(bits - prefix_len), saturating for ::/0
+        let address_count = 1u128
+            .checked_shl(u32::from(bits - prefix_len))
+            .unwrap_or(u128::MAX);
         Ok(address_count)
     } else {
         // Parse as integer
@@ -55,18 +88,210 @@ fn parse_tolerance(s: &str) -> Result<u64, String> {
     }
 }
 
+/// Policy for handling input lines that are not valid CIDRs.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OnError {
+    /// Silently drop invalid lines and merge the valid ones.
+    Skip,
+    /// Drop invalid lines, reporting each one (with its line number) on stderr.
+    Warn,
+    /// Abort on the first invalid line.
+    Fail,
+}
+
+/// Policy for CIDRs with host bits set beyond the prefix length, such as `10.0.0.1/24`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum HostBits {
+    /// Clear the host bits (`10.0.0.1/24` becomes `10.0.0.0/24`).
+    Normalize,
+    /// Treat the line as invalid, handled according to `--on-error`.
+    Reject,
+    /// Clear the host bits, reporting each such line (with its line number) on stderr.
+    Warn,
+}
+
+/// Algorithm used to choose which networks to merge.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Strategy {
+    /// Fast merging of neighbouring networks; may not find the best result.
+    Greedy,
+    /// The fewest networks within the tolerance, or the fewest extra addresses within
+    /// `--max-entries`. Slower on large inputs.
+    Optimal,
+}
+
+/// Format of the merge input.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    /// One CIDR (or address, or range) per line, with optional comments.
+    Plain,
+    /// CSV with the networks in the column selected by `--column`.
+    Csv,
+    /// A JSON document with the networks at the path given by `--select`.
+    Json,
+    /// A YAML document with the networks at the path given by `--select`.
+    Yaml,
+}
+
+/// Parses a `--le` value: a prefix length or `absorbed`.
+fn parse_length_bound(s: &str) -> Result<LengthBound, String> {
+    if s == "absorbed" {
+        return Ok(LengthBound::Absorbed);
+    }
+    match s.parse::<u8>() {
+        Ok(len) if len <= 128 => Ok(LengthBound::Length(len)),
+        _ => Err(format!(
+            "Invalid prefix length: {s}. Expected 0-128 or `absorbed`"
+        )),
+    }
+}
+
+/// Parses a `--where` value of the form `FIELD=VALUE`.
+fn parse_filter(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((field, value)) if !field.is_empty() => Ok((field.to_string(), value.to_string())),
+        _ => Err(format!("Invalid filter: {s}. Expected FIELD=VALUE")),
+    }
+}
+
+/// Parses a `--column` value: a 1-based column number or a header name.
+fn parse_column(s: &str) -> Result<CsvColumn, String> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(CsvColumn::Name(s.to_string()));
+    }
+    match s.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(CsvColumn::Index(number - 1)),
+        _ => Err(format!(
+            "Invalid column number: {s}. Columns are numbered from 1"
+        )),
+    }
+}
+
+/// Output format for the merged networks.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// One CIDR per line.
+    Plain,
+    /// A JSON object with per-network metadata and run totals.
+    Json,
+    /// One address range per line (`first-last`), joining adjacent networks.
+    Range,
+    /// An `iptables-restore` rule file appending one rule per network.
+    Iptables,
+    /// An nftables script filling an interval set.
+    NftSet,
+    /// An `ipset restore` file filling a `hash:net` set.
+    IpsetRestore,
+    /// A Cisco IOS prefix list.
+    Cisco,
+    /// A Junos prefix list or route filter list.
+    Junos,
+    /// BIRD prefix set definitions.
+    Bird,
+    /// An FRR prefix list.
+    Frr,
+}
+
+/// Output format for the `diff` subcommand.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DiffFormat {
+    /// One signed CIDR per line.
+    Plain,
+    /// A JSON object with the added and removed CIDRs.
+    Json,
+    /// One signed address range per line (`first-last`).
+    Range,
+}
+
+/// Set operations on whole CIDR lists, run instead of the default merge.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the addresses covered by both lists, as minimal CIDRs.
+    Intersect {
+        /// File of CIDRs, in the same format as the merge input.
+        #[arg(value_name = "FILE")]
+        a: PathBuf,
+        /// File of CIDRs, in the same format as the merge input.
+        #[arg(value_name = "FILE")]
+        b: PathBuf,
+    },
+    /// Print the addresses covered by any of the lists, as minimal CIDRs.
+    Union {
+        /// Files of CIDRs, in the same format as the merge input.
+        #[arg(required = true, num_args = 2.., value_name = "FILE")]
+        files: Vec<PathBuf>,
+    },
+    /// Compare the addresses covered by two lists and print what changed.
+    ///
+    /// Exits with 0 if both lists cover the same addresses, 1 if they differ, and 2 if
+    /// either list cannot be read.
+    Diff {
+        /// File of CIDRs before the change.
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+        /// File of CIDRs after the change.
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+        /// Output format.
+        ///
+        /// `plain` (the default) prints one CIDR per line, prefixed with `+` if it was
+        /// added or `-` if it was removed, in address order. `range` does the same with
+        /// address ranges (`first-last`) instead of CIDRs. `json` prints an object
+        /// with the `added` and `removed` CIDRs and their `added_addresses` and
+        /// `removed_addresses` counts.
+        #[arg(long, value_enum, default_value_t = DiffFormat::Plain)]
+        format: DiffFormat,
+    },
+    /// Expand every input network into subnets of a fixed prefix length.
+    Split {
+        /// Prefix length of the IPv4 subnets (e.g., `24`).
+        ///
+        /// IPv4 networks are left whole when only `--prefix-v6` is given. Networks that
+        /// are already at least this long are printed unchanged.
+        #[arg(long, value_name = "N", required_unless_present = "prefix_v6", value_parser = clap::value_parser!(u8).range(0..=32))]
+        prefix: Option<u8>,
+        /// Prefix length of the IPv6 subnets (e.g., `64`).
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=128))]
+        prefix_v6: Option<u8>,
+        /// Refuse to print more than N subnets.
+        ///
+        /// Guards against accidental huge expansions, such as a /8 into /32s. The
+        /// number of subnets is checked before anything is printed.
+        #[arg(long, value_name = "N", default_value_t = 1 << 20)]
+        max_subnets: u128,
+        /// File of CIDRs. If omitted, CIDRs are read from standard input.
+        #[arg(value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Print the free space in a container: every address not covered by the input.
+    Complement {
+        /// Block to list free space in (e.g., `10.0.0.0/8`).
+        #[arg(long, value_name = "CIDR")]
+        within: IpNet,
+        /// File of allocated CIDRs. If omitted, CIDRs are read from standard input.
+        #[arg(value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+}
+
 /// Command-line arguments for the CIDR merge utility.
 #[derive(Parser, Debug)]
 #[command(author, version, about = "CIDR merge utility", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Optional path to a file containing CIDRs (one per line).
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Files containing CIDRs (one per line), merged together. May be repeated.
     ///
-    /// If omitted, CIDRs are read from standard input. Empty lines are ignored.
-    /// Each non-empty line should contain a single IPv4 CIDR block in standard notation
-    /// (e.g., `10.0.0.0/24`).
-    #[arg(short, long)]
-    input: Option<PathBuf>,
-    /// Maximum number of extra addresses allowed when merging CIDRs.
+    /// If omitted, CIDRs are read from standard input. `-` stands for standard input,
+    /// and glob patterns such as `'lists/*.txt'` are expanded (they must match at least
+    /// one file). Empty lines are ignored. Each non-empty line should contain a single
+    /// IPv4 or IPv6 CIDR block in standard notation (e.g., `10.0.0.0/24` or
+    /// `2001:db8::/32`). With more than one input, every network remembers the inputs it
+    /// came from, shown by `--annotate` and `--format json`.
+    #[arg(short, long, value_name = "FILE", num_args = 1..)]
+    input: Vec<String>,
+    /// Maximum number of extra IPv4 addresses allowed when merging CIDRs.
     ///
     /// Defaults to `0`, which means only lossless (exact) merges are performed.
     /// When set to `N > 0`, the algorithm may merge networks even if the resulting
@@ -84,33 +309,610 @@ struct Args {
     /// - `--tolerance /16` - Allow up to 65536 extra addresses (equivalent to a /16 block)
     #[arg(short, long, default_value_t = 0, value_parser = parse_tolerance)]
     tolerance: u64,
+    /// Maximum number of extra IPv6 addresses allowed when merging CIDRs.
+    ///
+    /// IPv6 networks are merged independently of IPv4 networks and use this separate
+    /// budget. Accepts the same formats as `--tolerance`, with bit masks interpreted
+    /// against 128-bit addresses (e.g., `"/64"` = 2^64 addresses).
+    #[arg(long, default_value_t = 0, value_parser = parse_tolerance_v6)]
+    tolerance_v6: u128,
+    /// Treat the tolerances as a total budget for the whole run.
+    ///
+    /// By default each merge may add up to `--tolerance` extra addresses on its own,
+    /// so the total over-coverage can be much larger. With this flag the extra
+    /// addresses of all merges together never exceed `--tolerance` (IPv4) or
+    /// `--tolerance-v6` (IPv6), and merges are chosen cheapest-first until the budget
+    /// runs out.
+    #[arg(long)]
+    budget: bool,
+    /// Merge until at most N networks remain, IPv4 and IPv6 together.
+    ///
+    /// The merge that adds the fewest extra addresses, of either family, is applied
+    /// repeatedly, whatever the over-coverage, until the limit is met. The extra
+    /// addresses accepted for each family are reported on stderr. Fails if the limit
+    /// cannot be met, e.g. below 2 for dual-stack input, since networks of different
+    /// families are never merged. Cannot be combined with the tolerance options.
+    #[arg(long, value_name = "N", conflicts_with_all = ["tolerance", "tolerance_v6", "budget"])]
+    max_entries: Option<NonZeroUsize>,
+    /// Algorithm used to choose which networks to merge.
+    ///
+    /// `greedy` (the default) merges neighbouring networks as it finds them, or
+    /// cheapest-first with `--budget` and `--max-entries`. `optimal` finds the best
+    /// possible result: the fewest networks whose total extra addresses fit the
+    /// tolerance, or the fewest extra addresses within `--max-entries`. With `optimal`,
+    /// the tolerance is always a total budget, as with `--budget`.
+    #[arg(long, value_enum, default_value_t = Strategy::Greedy)]
+    strategy: Strategy,
+    /// Remove a range from the output. May be repeated.
+    ///
+    /// Each value is either a single CIDR (e.g., `10.1.0.0/16`) or the path of a file
+    /// of CIDRs in the same format as the input. Exclusions are applied after merging,
+    /// so tolerance merges never re-cover an excluded range; merged networks that
+    /// overlap one are split into the minimal list of CIDRs around it.
+    #[arg(long, value_name = "FILE|CIDR")]
+    exclude: Vec<String>,
     /// Validate that the input is already optimally merged. Exit code 1 if further merges are possible.
     #[arg(long)]
     check: bool,
+    /// Scan free-form text (logs, HTML, configs) for IPv4 addresses and CIDRs.
+    ///
+    /// Instead of expecting one CIDR per line, every IPv4 address or CIDR that stands
+    /// on its own as a word is extracted and merged; the rest of the text is ignored.
+    /// Dotted version strings such as `v1.2.3.4` or `1.2.3.4.5` are not matched.
+    #[arg(long, conflicts_with_all = ["on_error", "host_bits", "input_format"])]
+    extract: bool,
+    /// Format of the input.
+    ///
+    /// `plain` (the default) expects one CIDR per line. `csv` reads the networks from
+    /// the column selected by `--column`, and carries every other non-empty field
+    /// through the merge as an annotation (`header=value`), shown by `--annotate` and
+    /// `--format json`. Quoted fields are supported. `json` and `yaml` read the
+    /// networks selected by `--select` from a document such as AWS's `ip-ranges.json`.
+    #[arg(long, value_enum, default_value_t = InputFormat::Plain)]
+    input_format: InputFormat,
+    /// CSV column holding the networks: a header name, or a column number counted
+    /// from 1.
+    #[arg(
+        long,
+        value_name = "NAME|N",
+        value_parser = parse_column,
+        required_if_eq("input_format", "csv")
+    )]
+    column: Option<CsvColumn>,
+    /// Read the first CSV record as data instead of a header row.
+    ///
+    /// Columns must then be selected by number.
+    #[arg(long)]
+    no_header: bool,
+    /// Path to the networks in a JSON or YAML document, e.g. `.prefixes[].ip_prefix`.
+    ///
+    /// A chain of `.field` lookups, `[]` (every element of an array) and `[N]` (one
+    /// element). Entries without the field and `null` values are skipped. Required
+    /// with `--input-format json` and `yaml`.
+    #[arg(
+        long,
+        value_name = "PATH",
+        required_if_eq_any([("input_format", "json"), ("input_format", "yaml")]),
+        conflicts_with_all = ["on_error", "host_bits", "column", "no_header"]
+    )]
+    select: Option<String>,
+    /// Only keep document entries whose field has the given value. May be repeated.
+    ///
+    /// The field is looked up in the object holding each selected value (e.g. `region`
+    /// next to `ip_prefix`). Repeating a field accepts any of its values; different
+    /// fields must all match.
+    #[arg(
+        long = "where",
+        value_name = "FIELD=VALUE",
+        value_parser = parse_filter,
+        requires = "select"
+    )]
+    filters: Vec<(String, String)>,
+    /// What to do with input lines that are not valid CIDRs.
+    ///
+    /// `fail` (the default) aborts on the first invalid line. `skip` and `warn` drop
+    /// invalid lines and merge the rest; `warn` additionally reports every rejected
+    /// line and the total number of rejected lines on stderr.
+    #[arg(long, value_enum, default_value_t = OnError::Fail)]
+    on_error: OnError,
+    /// What to do with CIDRs whose address has bits set beyond the prefix length.
+    ///
+    /// `normalize` (the default) clears the host bits, so `10.0.0.1/24` is read as
+    /// `10.0.0.0/24`. `warn` does the same and reports every such line on stderr.
+    /// `reject` treats them as invalid lines, handled according to `--on-error`.
+    #[arg(long, value_enum, default_value_t = HostBits::Normalize)]
+    host_bits: HostBits,
+    /// Carry trailing comments through the merge.
+    ///
+    /// Comments (starting with `#` or `;`) are always stripped from the input. With this
+    /// flag, each output network is followed by a comment listing the combined
+    /// annotations of every input network it absorbed (e.g., `10.0.0.0/23 # prod-eu, prod-us`).
+    /// With more than one `--input`, the comment also names the inputs the absorbed
+    /// networks came from (e.g., `10.0.0.0/23 # prod-eu, prod-us (from a.txt, b.txt)`).
+    #[arg(long)]
+    annotate: bool,
+    /// Output format.
+    ///
+    /// `plain` (the default) prints one CIDR per line. `range` prints one address range
+    /// per line (`first-last`), joining adjacent networks that no single CIDR could
+    /// cover; `--annotate` and `--info` do not apply to it. `json` prints an object with
+    /// `input_count`, `output_count`, `extra_addresses` (addresses added by tolerance
+    /// merges), and a `networks` array describing each merged network: its
+    /// `address_count`, `first_address`, `last_address`, the input CIDRs it `absorbed`,
+    /// and any `annotations`.
+    ///
+    /// `iptables`, `nft-set` and `ipset-restore` print a rule file for a firewall, with
+    /// one entry per IPv4 network; they fail if any IPv6 network remains after merging,
+    /// and `--annotate` and `--info` do not apply to them. `iptables` writes an
+    /// `iptables-restore` file appending `-A <CHAIN> -s <CIDR> -j <TARGET>` rules to the
+    /// `filter` table, to be loaded with `iptables-restore --noflush`. `nft-set` writes a
+    /// script for `nft -f` that creates the interval set `--set-name` in the `ip filter`
+    /// table and replaces its elements. `ipset-restore` writes an `ipset restore` file
+    /// that creates the `hash:net` set `--set-name` and adds the networks to it.
+    ///
+    /// `cisco`, `junos`, `bird` and `frr` print a prefix list named `--list-name` for
+    /// BGP route filtering, with one `permit` entry per network, qualified by `--ge` and
+    /// `--le`; `--annotate` and `--info` do not apply to them.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+    format: OutputFormat,
+    /// Chain the `iptables` rules are appended to (e.g., `INPUT`).
+    #[arg(long, value_name = "CHAIN", required_if_eq("format", "iptables"))]
+    chain: Option<String>,
+    /// Target the `iptables` rules jump to (e.g., `DROP`), with any target options.
+    #[arg(long, value_name = "TARGET", required_if_eq("format", "iptables"))]
+    target: Option<String>,
+    /// Name of the set filled by `--format nft-set` and `--format ipset-restore`.
+    #[arg(
+        long,
+        value_name = "NAME",
+        required_if_eq_any([("format", "nft-set"), ("format", "ipset-restore")])
+    )]
+    set_name: Option<String>,
+    /// Name of the prefix list printed by `--format cisco`, `junos`, `bird` and `frr`.
+    ///
+    /// BIRD gets one set per address family; the IPv6 set is named with a `_v6` suffix.
+    #[arg(
+        long,
+        value_name = "NAME",
+        required_if_eq_any([
+            ("format", "cisco"),
+            ("format", "junos"),
+            ("format", "bird"),
+            ("format", "frr"),
+        ])
+    )]
+    list_name: Option<String>,
+    /// Minimum prefix length (`ge`) accepted by each prefix-list entry.
+    ///
+    /// Only applied to entries with a shorter prefix, so a value meant for IPv4 leaves
+    /// IPv6 entries alone.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=128))]
+    ge: Option<u8>,
+    /// Maximum prefix length (`le`) accepted by each prefix-list entry.
+    ///
+    /// A number applies the same bound to every entry that has a shorter prefix.
+    /// `absorbed` bounds each entry by the longest input network it absorbed, so the
+    /// more-specific routes that were merged away are still accepted; entries that
+    /// absorbed no longer network get no bound.
+    #[arg(long, value_name = "N|absorbed", value_parser = parse_length_bound)]
+    le: Option<LengthBound>,
+    /// Sequence number of the first entry of each Cisco or FRR prefix list.
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    seq_start: u32,
+    /// Increment between sequence numbers of Cisco and FRR prefix-list entries.
+    #[arg(long, value_name = "N", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    seq_step: u32,
+    /// Print addressing details for each output network.
+    ///
+    /// Shows the network and broadcast address, first and last usable host, netmask,
+    /// wildcard mask, address count and usable host count, as aligned columns under a
+    /// header. With `--format json`, prints a JSON array with one object per network
+    /// instead.
+    #[arg(long, conflicts_with = "annotate")]
+    info: bool,
+    /// Print merge statistics to stderr.
+    ///
+    /// Reports the number of input and output networks, duplicates dropped, covered
+    /// subnets removed, exact and tolerance merges performed, extra addresses
+    /// introduced, passes of the merge loop, and the addresses covered before and
+    /// after the merge. With `--exclude`, the output count, extra addresses and
+    /// addresses after describe the printed list, after the exclusions.
+    #[arg(long)]
+    stats: bool,
 }
 
-fn normalize_for_check(mut nets: Vec<Ipv4Net>) -> Vec<Ipv4Net> {
+/// Reads a file of CIDRs, naming the file in any error.
+fn read_nets_file(path: &Path) -> Result<Vec<IpNet>, String> {
+    let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    parse_ip_nets(BufReader::new(file)).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Expands `--input` values into the paths to read, in order.
+///
+/// `-` (standard input) and plain paths are kept as they are; values containing glob
+/// metacharacters are expanded and must match at least one path. URLs are refused, since
+/// inputs are only ever read from local files.
+fn expand_inputs(values: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    for value in values {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Err(format!(
+                "{value}: only local files can be read; download the document first"
+            ));
+        }
+
+        if !value.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(value));
+            continue;
+        }
+
+        let matched = glob::glob(value)
+            .map_err(|err| format!("{value}: invalid pattern: {err}"))?
+            .collect::<Result<Vec<PathBuf>, _>>()
+            .map_err(|err| err.to_string())?;
+        if matched.is_empty() {
+            return Err(format!("{value}: no files match the pattern"));
+        }
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// Reads CIDRs from a subcommand's optional input file, or from stdin.
+fn read_input(input: Option<PathBuf>) -> Result<Vec<IpNet>, String> {
+    match input {
+        Some(path) => read_nets_file(&path),
+        None => parse_ip_nets(BufReader::new(io::stdin().lock())).map_err(|err| err.to_string()),
+    }
+}
+
+/// Reads the networks named by an `--exclude` value: a network written like an input
+/// line (a CIDR, bare address, dotted mask or range), or a file of CIDRs.
+///
+/// A value that looks like a network but does not parse, and names no file, reports
+/// the parse error rather than a missing file.
+fn read_exclusion(value: &str) -> Result<Vec<IpNet>, String> {
+    let path = Path::new(value);
+    match parse_ip_nets(value.as_bytes()) {
+        Ok(nets) if !nets.is_empty() => Ok(nets),
+        Err(err) if looks_like_network(value) && !path.exists() => {
+            Err(format!("--exclude {value}: {err}"))
+        }
+        _ => read_nets_file(path),
+    }
+}
+
+/// Returns whether `value` looks like a network rather than a file name: it starts with
+/// a hex digit or `:`, contains an address or prefix separator, and has no characters
+/// besides hex digits, separators and spaces (so `blocked.txt` is still a file name).
+fn looks_like_network(value: &str) -> bool {
+    let separators = ['/', '.', ':', '-'];
+    value.starts_with(|c: char| c.is_ascii_hexdigit() || c == ':')
+        && value.contains(separators)
+        && value
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c.is_ascii_whitespace() || separators.contains(&c))
+}
+
+/// Returns the number of addresses in `merged` that none of `inputs` covered, for IPv4
+/// and IPv6 in that order.
+fn extra_addresses_by_family(inputs: &[AnnotatedNet], merged: &[IpNet]) -> [u128; 2] {
+    [false, true].map(|ipv6| {
+        let family_inputs: Vec<AnnotatedNet> = inputs
+            .iter()
+            .filter(|entry| matches!(entry.net, IpNet::V6(_)) == ipv6)
+            .map(|entry| AnnotatedNet::from(entry.net))
+            .collect();
+        let family_merged: Vec<IpNet> = merged
+            .iter()
+            .filter(|net| matches!(net, IpNet::V6(_)) == ipv6)
+            .copied()
+            .collect();
+        MergeReport::new(&family_inputs, &family_merged).extra_addresses
+    })
+}
+
+/// Runs a set-operation subcommand, printing the result one CIDR per line.
+fn run_command(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    let result = match command {
+        Command::Intersect { a, b } => intersect_ip_nets(read_nets_file(&a)?, read_nets_file(&b)?),
+        Command::Union { files } => {
+            let mut result = Vec::new();
+            for path in &files {
+                result = union_ip_nets(result, read_nets_file(path)?);
+            }
+            result
+        }
+        Command::Complement { within, input } => complement_ip_nets(within, read_input(input)?),
+        Command::Diff { old, new, format } => return run_diff(&old, &new, format),
+        Command::Split {
+            prefix,
+            prefix_v6,
+            max_subnets,
+            input,
+        } => return run_split(read_input(input)?, prefix, prefix_v6, max_subnets),
+    };
+
+    for net in result {
+        println!("{net}");
+    }
+    Ok(())
+}
+
+/// Builds the rule file for one of the firewall output formats.
+///
+/// Fails if any IPv6 network remains, since every format fills an IPv4-only table or set.
+fn firewall_rules(args: &Args, merged: &[IpNet]) -> Result<String, String> {
+    let mut v4 = Vec::new();
+    let mut v6_count = 0;
+    for net in merged {
+        match net {
+            IpNet::V4(net) => v4.push(*net),
+            IpNet::V6(_) => v6_count += 1,
+        }
+    }
+    if v6_count > 0 {
+        let format = args
+            .format
+            .to_possible_value()
+            .expect("no skipped variants");
+        return Err(format!(
+            "--format {} only supports IPv4 networks, but the output has {v6_count} IPv6 network(s)",
+            format.get_name()
+        ));
+    }
+
+    // clap requires the options each format needs
+    let set_name = args.set_name.as_deref().unwrap_or_default();
+    Ok(match args.format {
+        OutputFormat::Iptables => iptables_rules(
+            &v4,
+            args.chain.as_deref().unwrap_or_default(),
+            args.target.as_deref().unwrap_or_default(),
+        ),
+        OutputFormat::NftSet => nft_set(&v4, set_name),
+        _ => ipset_restore(&v4, set_name),
+    })
+}
+
+/// Runs the `diff` subcommand, exiting with 1 if the lists differ and 2 on errors.
+fn run_diff(old: &Path, new: &Path, format: DiffFormat) -> Result<(), Box<dyn std::error::Error>> {
+    match print_diff(old, new, format) {
+        Ok(false) => Ok(()),
+        Ok(true) => process::exit(1),
+        Err(err) => {
+            eprintln!("Error: {err}");
+            process::exit(2);
+        }
+    }
+}
+
+/// Prints the difference between two lists, returning whether they differ.
+fn print_diff(
+    old: &Path,
+    new: &Path,
+    format: DiffFormat,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let diff = diff_ip_nets(read_nets_file(old)?, read_nets_file(new)?);
+    let changed = !diff.is_empty();
+
+    if format == DiffFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        // Removed and added addresses never overlap, so sorting by first address
+        // interleaves both sides in address order
+        let mut lines: Vec<(IpAddr, char, String)> = Vec::new();
+        for (sign, nets) in [('-', diff.removed), ('+', diff.added)] {
+            if format == DiffFormat::Range {
+                lines.extend(
+                    ip_nets_to_ranges(nets)
+                        .into_iter()
+                        .map(|(start, end)| (start, sign, format!("{start}-{end}"))),
+                );
+            } else {
+                lines.extend(
+                    nets.into_iter()
+                        .map(|net| (net.network(), sign, net.to_string())),
+                );
+            }
+        }
+        lines.sort();
+        for (_, sign, text) in lines {
+            println!("{sign} {text}");
+        }
+    }
+    Ok(changed)
+}
+
+/// Runs the `split` subcommand, streaming the subnets to stdout.
+fn run_split(
+    nets: Vec<IpNet>,
+    prefix: Option<u8>,
+    prefix_v6: Option<u8>,
+    max_subnets: u128,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let count = split_count(&nets, prefix, prefix_v6);
+    if count > max_subnets {
+        return Err(format!(
+            "splitting would produce {count} subnets, more than --max-subnets {max_subnets}"
+        )
+        .into());
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = split_ip_nets(nets, prefix, prefix_v6)?
+        .try_for_each(|net| writeln!(out, "{net}"))
+        .and_then(|()| out.flush());
+    match written {
+        // The reader (e.g. `head`) has seen enough; stop quietly
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Prints addressing details for each network as aligned columns under a header.
+fn print_info_table(nets: &[IpNet]) {
+    const COLUMNS: usize = 9;
+    // Counts are right-aligned so that their magnitudes line up
+    const FIRST_COUNT: usize = 7;
+
+    let header = [
+        "network",
+        "address",
+        "broadcast",
+        "first usable",
+        "last usable",
+        "netmask",
+        "wildcard",
+        "addresses",
+        "usable",
+    ]
+    .map(String::from);
+    let rows: Vec<[String; COLUMNS]> = std::iter::once(header)
+        .chain(nets.iter().map(|net| {
+            let info = NetworkInfo::new(net);
+            [
+                info.network.to_string(),
+                info.network_address.to_string(),
+                info.broadcast
+                    .map_or_else(|| "-".to_string(), |addr| addr.to_string()),
+                info.first_usable.to_string(),
+                info.last_usable.to_string(),
+                info.netmask.to_string(),
+                info.wildcard.to_string(),
+                info.address_count.to_string(),
+                info.usable_count.to_string(),
+            ]
+        }))
+        .collect();
+
+    let mut widths = [0; COLUMNS];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                if column >= FIRST_COUNT {
+                    format!("{cell:>width$}")
+                } else {
+                    format!("{cell:<width$}")
+                }
+            })
+            .collect();
+        println!("{}", cells.join("  "));
+    }
+}
+
+fn normalize_for_check(mut nets: Vec<IpNet>) -> Vec<IpNet> {
     // Check mode must detect any change the merge step would perform, including dropping
     // duplicates. Sorting provides a stable ordering for comparison while preserving the
     // original multiplicity so that repeated CIDRs remain visible as a behavioral change.
-    nets.sort_by(|a, b| {
-        u32::from(a.addr())
-            .cmp(&u32::from(b.addr()))
-            .then(a.prefix_len().cmp(&b.prefix_len()))
-    });
+    // `IpNet` orders IPv4 before IPv6, then by address and prefix length, which matches
+    // the order in which the merge emits its output.
+    nets.sort();
     nets
 }
 
+/// Reads the networks of one merge input, naming `source` (if any) in errors and
+/// warnings.
+///
+/// Line-per-CIDR and CSV input is parsed according to the `--on-error` and
+/// `--host-bits` policies, reporting skipped and normalized lines on stderr. With
+/// `--extract`, the input is scanned as free-form text instead, and JSON and YAML
+/// documents are queried with `--select`.
+fn read_merge_input(
+    reader: Box<dyn BufRead>,
+    args: &Args,
+    source: Option<&str>,
+) -> Result<Vec<AnnotatedNet>, String> {
+    let prefix = source.map(|name| format!("{name}: ")).unwrap_or_default();
+
+    if args.extract {
+        let nets = extract_ipv4_nets(reader).map_err(|err| format!("{prefix}{err}"))?;
+        return Ok(nets.into_iter().map(|net| IpNet::V4(net).into()).collect());
+    }
+
+    if let (InputFormat::Json | InputFormat::Yaml, Some(selector)) =
+        (args.input_format, &args.select)
+    {
+        let format = match args.input_format {
+            InputFormat::Yaml => DocumentFormat::Yaml,
+            _ => DocumentFormat::Json,
+        };
+        let query = DocumentQuery {
+            selector: selector.clone(),
+            filters: args.filters.clone(),
+        };
+        let nets = parse_document_ip_nets(reader, format, &query)
+            .map_err(|err| format!("{prefix}{err}"))?;
+        return Ok(nets.into_iter().map(AnnotatedNet::from).collect());
+    }
+
+    let options = ParseOptions {
+        error_mode: match args.on_error {
+            OnError::Skip | OnError::Warn => ErrorMode::Skip,
+            OnError::Fail => ErrorMode::FailFast,
+        },
+        host_bits: match args.host_bits {
+            HostBits::Normalize => HostBitsMode::Normalize,
+            HostBits::Reject => HostBitsMode::Reject,
+            HostBits::Warn => HostBitsMode::Warn,
+        },
+    };
+    let parsed = match (args.input_format, &args.column) {
+        (InputFormat::Csv, Some(column)) => {
+            let csv = CsvOptions {
+                column: column.clone(),
+                has_headers: !args.no_header,
+            };
+            parse_csv_ip_nets(reader, &csv, &options)
+        }
+        _ => parse_ip_nets_with_options(reader, &options).map(|mut parsed| ParsedRecords {
+            rejected: std::mem::take(&mut parsed.rejected),
+            normalized: std::mem::take(&mut parsed.normalized),
+            nets: parsed.into_annotated(),
+        }),
+    }
+    .map_err(|errors| format!("{prefix}{}", errors[0]))?;
+
+    for err in &parsed.normalized {
+        eprintln!("warning: clearing {prefix}{err}");
+    }
+
+    if args.on_error == OnError::Warn && !parsed.rejected.is_empty() {
+        for err in &parsed.rejected {
+            eprintln!("warning: skipping {prefix}{err}");
+        }
+        eprintln!(
+            "warning: skipped {} invalid line(s){}",
+            parsed.rejected.len(),
+            source.map(|name| format!(" in {name}")).unwrap_or_default()
+        );
+    }
+
+    Ok(parsed.nets)
+}
+
 /// Main entry point for the CIDR merge utility.
 ///
-/// Reads IPv4 CIDR blocks from a file or standard input, merges them into a minimal
-/// covering set, and prints the results to standard output (one CIDR per line).
+/// Reads IPv4 and IPv6 CIDR blocks from a file or standard input, merges each family
+/// into a minimal covering set, and prints the results to standard output (one CIDR per line).
+///
+/// The `intersect`, `union`, `complement`, `diff` and `split` subcommands run set
+/// operations instead.
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The input file cannot be opened or read
-/// - Any line contains an invalid CIDR block
+/// - Any line contains an invalid CIDR block (unless `--on-error` is `skip` or `warn`)
+/// - An `--exclude` value is neither a CIDR nor a readable file of valid CIDRs
 /// - Standard input cannot be read
 ///
 /// # Exit Codes
@@ -120,14 +922,111 @@ fn normalize_for_check(mut nets: Vec<Ipv4Net>) -> Vec<Ipv4Net> {
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let reader: Box<dyn BufRead> = match args.input {
-        Some(path) => Box::new(BufReader::new(File::open(path)?)),
-        None => Box::new(BufReader::new(io::stdin().lock())),
+    if let Some(command) = args.command {
+        return run_command(command);
+    }
+
+    if !matches!(args.input_format, InputFormat::Csv) {
+        let csv_option = if args.column.is_some() {
+            Some("--column")
+        } else {
+            args.no_header.then_some("--no-header")
+        };
+        if let Some(option) = csv_option {
+            return Err(format!("{option} requires --input-format csv").into());
+        }
+    }
+
+    let paths = if args.input.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        expand_inputs(&args.input)?
     };
+    // Only name stdin when there are other inputs to tell it apart from
+    let tag_sources = paths.len() > 1;
+
+    let mut inputs: Vec<AnnotatedNet> = Vec::new();
+    for path in &paths {
+        let (reader, source): (Box<dyn BufRead>, Option<String>) = if path == Path::new("-") {
+            let source = tag_sources.then(|| "<stdin>".to_string());
+            (Box::new(BufReader::new(io::stdin().lock())), source)
+        } else {
+            let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+            (
+                Box::new(BufReader::new(file)),
+                Some(path.display().to_string()),
+            )
+        };
 
-    let nets =
-        parse_ipv4_nets(reader).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
-    let merged = merge_ipv4_nets(nets.clone(), args.tolerance);
+        let mut nets = read_merge_input(reader, &args, source.as_deref())?;
+        if tag_sources && let Some(source) = source {
+            for entry in &mut nets {
+                entry.sources = vec![source.clone()];
+            }
+        }
+        inputs.extend(nets);
+    }
+    let nets: Vec<IpNet> = inputs.iter().map(|entry| entry.net).collect();
+    let merge_options = MergeOptions {
+        tolerance_v4: args.tolerance,
+        tolerance_v6: args.tolerance_v6,
+        tolerance_mode: if args.budget {
+            ToleranceMode::Budget
+        } else {
+            ToleranceMode::PerMerge
+        },
+        max_entries: args.max_entries.map(NonZeroUsize::get),
+        strategy: match args.strategy {
+            Strategy::Greedy => MergeStrategy::Greedy,
+            Strategy::Optimal => MergeStrategy::Optimal,
+        },
+    };
+    let (mut merged, mut stats) = merge_ip_nets_with_options(nets.clone(), &merge_options);
+
+    if !args.exclude.is_empty() {
+        let mut excluded = Vec::new();
+        for value in &args.exclude {
+            excluded.extend(read_exclusion(value)?);
+        }
+        merged = exclude_ip_nets(merged, excluded);
+
+        // Describe the list that is printed, not the merge before the exclusions
+        let report = MergeReport::new(&inputs, &merged);
+        stats.output_count = report.output_count;
+        stats.extra_addresses = report.extra_addresses;
+        stats.addresses_after = report
+            .networks
+            .iter()
+            .map(|network| network.address_count)
+            .fold(0, u128::saturating_add);
+    }
+
+    if let Some(max_entries) = args.max_entries {
+        if merged.len() > max_entries.get() {
+            return Err(format!(
+                "--max-entries {max_entries} cannot be met: the output needs {} networks",
+                merged.len()
+            )
+            .into());
+        }
+
+        let accepted: Vec<String> = ["IPv4", "IPv6"]
+            .into_iter()
+            .zip(extra_addresses_by_family(&inputs, &merged))
+            .filter(|&(_, extra)| extra > 0)
+            .map(|(family, extra)| format!("{extra} extra {family} address(es)"))
+            .collect();
+        if !accepted.is_empty() {
+            eprintln!(
+                "note: accepted {} to fit in {max_entries} entries",
+                accepted.join(" and ")
+            );
+        }
+    }
+
+    if args.stats {
+        eprintln!("{stats}");
+    }
 
     if args.check {
         let normalized_input = normalize_for_check(nets);
@@ -139,8 +1038,69 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    for net in merged {
-        println!("{net}");
+    match args.format {
+        OutputFormat::Json if args.info => {
+            let info: Vec<NetworkInfo> = merged.iter().map(NetworkInfo::new).collect();
+            println!("{}", serde_json::to_string_pretty(&info)?);
+        }
+        OutputFormat::Plain if args.info => print_info_table(&merged),
+        OutputFormat::Json => {
+            let report = MergeReport::new(&inputs, &merged);
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        OutputFormat::Plain if args.annotate => {
+            for network in MergeReport::new(&inputs, &merged).networks {
+                let mut comment = network.annotations.join(", ");
+                if !network.sources.is_empty() {
+                    let sources = format!("from {}", network.sources.join(", "));
+                    comment = if comment.is_empty() {
+                        sources
+                    } else {
+                        format!("{comment} ({sources})")
+                    };
+                }
+
+                if comment.is_empty() {
+                    println!("{}", network.network);
+                } else {
+                    println!("{} # {comment}", network.network);
+                }
+            }
+        }
+        OutputFormat::Plain => {
+            for net in merged {
+                println!("{net}");
+            }
+        }
+        OutputFormat::Range => {
+            for (start, end) in ip_nets_to_ranges(merged) {
+                println!("{start}-{end}");
+            }
+        }
+        OutputFormat::Iptables | OutputFormat::NftSet | OutputFormat::IpsetRestore => {
+            print!("{}", firewall_rules(&args, &merged)?);
+        }
+        OutputFormat::Cisco | OutputFormat::Junos | OutputFormat::Bird | OutputFormat::Frr => {
+            if let (Some(ge), Some(LengthBound::Length(le))) = (args.ge, args.le)
+                && ge > le
+            {
+                return Err(format!("--ge {ge} is longer than --le {le}").into());
+            }
+            let options = PrefixListOptions {
+                syntax: match args.format {
+                    OutputFormat::Junos => RouterSyntax::Junos,
+                    OutputFormat::Bird => RouterSyntax::Bird,
+                    OutputFormat::Frr => RouterSyntax::Frr,
+                    _ => RouterSyntax::Cisco,
+                },
+                name: args.list_name.clone().unwrap_or_default(),
+                ge: args.ge,
+                le: args.le,
+                sequence_start: args.seq_start,
+                sequence_step: args.seq_step,
+            };
+            print!("{}", prefix_list(&nets, &merged, &options));
+        }
     }
 
     Ok(())
@@ -148,7 +1108,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_tolerance;
+    use super::{parse_tolerance, parse_tolerance_v6};
 
     #[test]
     fn test_parse_tolerance_integer() {
@@ -196,4 +1156,20 @@ mod tests {
         // Test that /23 equals 512 (another common use case)
         assert_eq!(parse_tolerance("/23").unwrap(), 512);
     }
+
+    #[test]
+    fn test_parse_tolerance_v6_bit_mask() {
+        assert_eq!(parse_tolerance_v6("/128").unwrap(), 1);
+        assert_eq!(parse_tolerance_v6("/64").unwrap(), 1u128 << 64);
+        assert_eq!(parse_tolerance_v6("/48").unwrap(), 1u128 << 80);
+        // 2^128 does not fit in a u128 and saturates
+        assert_eq!(parse_tolerance_v6("/0").unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn test_parse_tolerance_v6_integer_and_invalid() {
+        assert_eq!(parse_tolerance_v6("512").unwrap(), 512);
+        assert!(parse_tolerance_v6("/129").is_err());
+        assert!(parse_tolerance_v6("abc").is_err());
+    }
 }
@@ -0,0 +1,376 @@
+//! Optimal merging by dynamic programming over a binary prefix trie.
+//!
+//! After a lossless merge the networks are disjoint and form the leaves of a compressed
+//! binary trie, whose inner nodes are the smallest prefixes covering two or more of
+//! them. Any valid aggregation covers each inner node either with that node's prefix
+//! or with separate aggregations of its two subtrees; prefixes strictly between two
+//! trie nodes are never better than the lower node itself. For every node we record
+//! the fewest extra addresses needed to cover its leaves with exactly `k` prefixes,
+//! which combines bottom-up by a min-plus convolution of the two children.
+//!
+//! The trie is at most `N::BITS` levels deep. The work is quadratic in the number of
+//! networks in the worst case, or linear in the number of networks times the entry
+//! limit when one is given. With a budget, counts whose cost already exceeds it are
+//! dropped at every node, which keeps small budgets cheap.
+
+use crate::{
+    IpBlock, MergeStats, find_covering_supernet_impl, merge_nets_counted,
+    network_address_count_impl,
+};
+
+/// The constraint an optimal merge must satisfy.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Limit {
+    /// Use as few networks as possible, adding at most this many addresses in total.
+    Budget(u128),
+    /// Add as few addresses as possible, using at most this many networks.
+    MaxEntries(usize),
+}
+
+/// Merges `nets` into the best set of networks allowed by `limit`.
+///
+/// With [`Limit::Budget`], the result has the fewest networks of any aggregation whose
+/// total over-coverage fits the budget, and among those the least over-coverage. With
+/// [`Limit::MaxEntries`], the result has the least over-coverage of any aggregation
+/// with at most that many networks (but always at least one), and among those the
+/// fewest networks. Counters are added to `stats`.
+pub(crate) fn merge_optimal<N: IpBlock>(
+    nets: Vec<N>,
+    limit: Limit,
+    stats: &mut MergeStats,
+) -> Vec<N> {
+    let leaves = merge_nets_counted(nets, 0, stats);
+    if leaves.is_empty() {
+        return leaves;
+    }
+
+    let aggregation = Aggregation::new(leaves, limit);
+    let root = &aggregation.trie.nodes[aggregation.root];
+    let count = match limit {
+        // Costs above the budget were dropped, and the lossless aggregation always
+        // fits, so the first count left is the smallest feasible one
+        Limit::Budget(_) => Some(0),
+        Limit::MaxEntries(_) => root
+            .costs
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &cost)| cost)
+            .map(|(idx, _)| idx),
+    }
+    .map_or(aggregation.trie.leaves.len(), |idx| root.min_count + idx);
+
+    aggregation.emit(count, stats)
+}
+
+/// Merges the networks of two families into the aggregation with the fewest extra
+/// addresses that uses at most `max_entries` networks in total, and among those the
+/// fewest networks.
+///
+/// Extra addresses of both families are added together. Each family keeps at least
+/// one network, so two families always need at least two. Both lists must be
+/// non-empty. Counters are added to `stats_a` and `stats_b`.
+pub(crate) fn merge_optimal_to_limit<A: IpBlock, B: IpBlock>(
+    a: Vec<A>,
+    b: Vec<B>,
+    max_entries: usize,
+    stats_a: &mut MergeStats,
+    stats_b: &mut MergeStats,
+) -> (Vec<A>, Vec<B>) {
+    let limit = Limit::MaxEntries(max_entries);
+    let a = Aggregation::new(merge_nets_counted(a, 0, stats_a), limit);
+    let b = Aggregation::new(merge_nets_counted(b, 0, stats_b), limit);
+    let (min_a, max_a) = a.counts();
+    let (min_b, max_b) = b.counts();
+    let max_entries = max_entries.max(min_a + min_b);
+
+    // Costs never grow with the number of prefixes, so for each count of `a`, `b` does
+    // best with as many prefixes as remain. `fewest_b[i]` is the smallest count of `b`
+    // that costs as little as `min_b + i` prefixes do.
+    let mut fewest_b: Vec<usize> = Vec::with_capacity(max_b - min_b + 1);
+    for count in min_b..=max_b {
+        let fewest = match fewest_b.last() {
+            Some(&prev) if b.cost(prev) == b.cost(count) => prev,
+            _ => count,
+        };
+        fewest_b.push(fewest);
+    }
+
+    let (_, count_a, count_b) = (min_a..=max_a.min(max_entries - min_b))
+        .map(|count_a| {
+            let count_b = fewest_b[(max_entries - count_a).min(max_b) - min_b];
+            let cost = a.cost(count_a).saturating_add(b.cost(count_b));
+            ((cost, count_a + count_b), count_a, count_b)
+        })
+        .min_by_key(|&(key, _, _)| key)
+        .expect("each family has at least one count");
+
+    (a.emit(count_a, stats_a), b.emit(count_b, stats_b))
+}
+
+/// The trie over the lossless merge of one family, from which an aggregation with any
+/// recorded number of prefixes can be emitted.
+struct Aggregation<N> {
+    trie: Trie<N>,
+    root: usize,
+}
+
+impl<N: IpBlock> Aggregation<N> {
+    /// Builds the trie over `leaves`, which must be sorted, disjoint and non-empty.
+    fn new(leaves: Vec<N>, limit: Limit) -> Self {
+        let (cap, budget) = match limit {
+            Limit::Budget(budget) => (leaves.len(), budget),
+            Limit::MaxEntries(max_entries) => (max_entries.clamp(1, leaves.len()), u128::MAX),
+        };
+        let mut covered = Vec::with_capacity(leaves.len() + 1);
+        covered.push(0u128);
+        for leaf in &leaves {
+            let total = covered[covered.len() - 1];
+            covered.push(u128::saturating_add(
+                total,
+                network_address_count_impl(leaf),
+            ));
+        }
+
+        let last = leaves.len() - 1;
+        let mut trie = Trie {
+            leaves,
+            covered,
+            cap,
+            budget,
+            nodes: Vec::new(),
+        };
+        let root = trie.build(0, last);
+        Aggregation { trie, root }
+    }
+
+    /// Returns the smallest and largest number of prefixes recorded for the whole
+    /// family.
+    fn counts(&self) -> (usize, usize) {
+        let root = &self.trie.nodes[self.root];
+        (root.min_count, root.min_count + root.costs.len() - 1)
+    }
+
+    /// Returns the extra addresses of the best aggregation with `count` prefixes.
+    fn cost(&self, count: usize) -> u128 {
+        Trie::cost(&self.trie.nodes[self.root], count)
+    }
+
+    /// Returns the best aggregation with `count` prefixes.
+    fn emit(&self, count: usize, stats: &mut MergeStats) -> Vec<N> {
+        let mut merged = Vec::with_capacity(count);
+        self.trie.emit(self.root, count, &mut merged, stats);
+        merged
+    }
+}
+
+/// A node of the compressed trie.
+struct Node<N> {
+    /// The prefix of the node: a leaf network, or the smallest prefix covering both
+    /// children.
+    net: N,
+    /// Range of leaf indices below the node, inclusive.
+    leaves: (usize, usize),
+    /// Left and right child, for inner nodes.
+    children: Option<(usize, usize)>,
+    /// Smallest number of prefixes that can cover the node's leaves within the budget.
+    min_count: usize,
+    /// `costs[k - min_count]` is the fewest extra addresses needed to cover the node's
+    /// leaves with exactly `k` prefixes, or `u128::MAX` if that is impossible.
+    costs: Vec<u128>,
+}
+
+struct Trie<N> {
+    /// Sorted, disjoint networks at the bottom of the trie.
+    leaves: Vec<N>,
+    /// `covered[i]` is the number of addresses in the first `i` leaves.
+    covered: Vec<u128>,
+    /// Largest number of prefixes worth tracking per node.
+    cap: usize,
+    /// Largest total cost worth tracking; anything above it can never be used.
+    budget: u128,
+    nodes: Vec<Node<N>>,
+}
+
+impl<N: IpBlock> Trie<N> {
+    /// Builds the subtree over leaves `lo..=hi` and returns its node index.
+    fn build(&mut self, lo: usize, hi: usize) -> usize {
+        if lo == hi {
+            return self.push(Node {
+                net: self.leaves[lo],
+                leaves: (lo, hi),
+                children: None,
+                min_count: 1,
+                costs: vec![0],
+            });
+        }
+
+        let net = find_covering_supernet_impl(&self.leaves[lo], &self.leaves[hi])
+            .expect("leaves of one family always have a covering supernet");
+        // Both halves of `net` hold at least one leaf, or a smaller prefix would cover
+        // them all.
+        let upper_half = net.start() + (1u128 << (N::BITS - net.prefix() - 1));
+        let split = lo + self.leaves[lo..=hi].partition_point(|leaf| leaf.start() < upper_half);
+
+        let left = self.build(lo, split - 1);
+        let right = self.build(split, hi);
+
+        let extra = network_address_count_impl(&net)
+            .saturating_sub(self.covered[hi + 1].saturating_sub(self.covered[lo]));
+        let (min_count, costs) = self.combine(extra, &self.nodes[left], &self.nodes[right]);
+
+        self.push(Node {
+            net,
+            leaves: (lo, hi),
+            children: Some((left, right)),
+            min_count,
+            costs,
+        })
+    }
+
+    fn push(&mut self, node: Node<N>) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Costs of an inner node whose own prefix adds `extra` addresses, as the node's
+    /// `min_count` and `costs`.
+    fn combine(&self, extra: u128, left: &Node<N>, right: &Node<N>) -> (usize, Vec<u128>) {
+        let split_min = left.min_count + right.min_count;
+        let max_count =
+            (left.min_count + left.costs.len() - 1) + (right.min_count + right.costs.len() - 1);
+        let max_count = max_count.min(self.cap);
+
+        // Index 0 is one prefix: the node itself
+        let mut costs = vec![u128::MAX; max_count];
+        costs[0] = extra;
+        for (a, &left_cost) in left.costs.iter().enumerate() {
+            if left_cost == u128::MAX {
+                continue;
+            }
+            for (b, &right_cost) in right.costs.iter().enumerate() {
+                let Some(slot) = costs.get_mut(split_min + a + b - 1) else {
+                    break;
+                };
+                *slot = (*slot).min(left_cost.saturating_add(right_cost));
+            }
+        }
+
+        // Drop counts whose cost can never fit; the lossless split always does
+        let skip = costs
+            .iter()
+            .position(|&cost| cost <= self.budget)
+            .unwrap_or(0);
+        costs.drain(..skip);
+        (skip + 1, costs)
+    }
+
+    /// Returns the cost of covering `node` with exactly `count` prefixes.
+    fn cost(node: &Node<N>, count: usize) -> u128 {
+        count
+            .checked_sub(node.min_count)
+            .and_then(|idx| node.costs.get(idx))
+            .copied()
+            .unwrap_or(u128::MAX)
+    }
+
+    /// Appends the `count` prefixes of the best aggregation of `node` to `out`.
+    fn emit(&self, node: usize, count: usize, out: &mut Vec<N>, stats: &mut MergeStats) {
+        let node = &self.nodes[node];
+        let Some((left, right)) = node.children else {
+            out.push(node.net);
+            return;
+        };
+
+        if count == 1 {
+            out.push(node.net);
+            stats.tolerance_merges += 1;
+            stats.covered_removed += node.leaves.1 - node.leaves.0 - 1;
+            return;
+        }
+
+        let target = Self::cost(node, count);
+        let (left_node, right_node) = (&self.nodes[left], &self.nodes[right]);
+        let left_count = (1..count)
+            .find(|&a| {
+                let cost =
+                    Self::cost(left_node, a).saturating_add(Self::cost(right_node, count - a));
+                cost == target
+            })
+            .expect("every recorded cost has a split that produces it");
+
+        self.emit(left, left_count, out, stats);
+        self.emit(right, count - left_count, out, stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipnet::Ipv4Net;
+
+    fn v4(list: &[&str]) -> Vec<Ipv4Net> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn run(nets: &[&str], limit: Limit) -> (Vec<Ipv4Net>, MergeStats) {
+        let mut stats = MergeStats::default();
+        let merged = merge_optimal(v4(nets), limit, &mut stats);
+        (merged, stats)
+    }
+
+    #[test]
+    fn budget_of_zero_is_lossless() {
+        let (merged, _) = run(
+            &["10.0.0.0/24", "10.0.1.0/24", "10.0.3.0/24"],
+            Limit::Budget(0),
+        );
+
+        assert_eq!(merged, v4(&["10.0.0.0/23", "10.0.3.0/24"]));
+    }
+
+    #[test]
+    fn budget_picks_fewest_networks_that_fit() {
+        let nets = ["10.0.0.0/24", "10.0.2.0/24", "10.0.4.0/24", "10.0.6.0/24"];
+
+        assert_eq!(run(&nets, Limit::Budget(511)).0.len(), 4);
+        assert_eq!(
+            run(&nets, Limit::Budget(512)).0,
+            v4(&["10.0.0.0/22", "10.0.4.0/24", "10.0.6.0/24"])
+        );
+        assert_eq!(run(&nets, Limit::Budget(1024)).0, v4(&["10.0.0.0/21"]));
+    }
+
+    #[test]
+    fn max_entries_picks_least_over_coverage() {
+        let nets = ["10.0.0.0/24", "10.0.2.0/24", "10.0.8.0/24", "10.0.16.0/24"];
+
+        let (merged, stats) = run(&nets, Limit::MaxEntries(3));
+        assert_eq!(merged, v4(&["10.0.0.0/22", "10.0.8.0/24", "10.0.16.0/24"]));
+        assert_eq!(stats.tolerance_merges, 1);
+
+        let (merged, _) = run(&nets, Limit::MaxEntries(1));
+        assert_eq!(merged, v4(&["10.0.0.0/19"]));
+
+        let (merged, _) = run(&nets, Limit::MaxEntries(0));
+        assert_eq!(merged, v4(&["10.0.0.0/19"]));
+    }
+
+    #[test]
+    fn max_entries_above_lossless_count_is_lossless() {
+        let (merged, _) = run(&["10.0.0.0/24", "10.0.2.0/24"], Limit::MaxEntries(5));
+
+        assert_eq!(merged, v4(&["10.0.0.0/24", "10.0.2.0/24"]));
+    }
+
+    #[test]
+    fn single_network_and_whole_space() {
+        assert_eq!(
+            run(&["10.0.0.0/8"], Limit::Budget(0)).0,
+            v4(&["10.0.0.0/8"])
+        );
+        assert_eq!(
+            run(&["0.0.0.0/1", "192.0.0.0/2"], Limit::MaxEntries(1)).0,
+            v4(&["0.0.0.0/0"])
+        );
+    }
+}
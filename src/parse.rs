@@ -0,0 +1,1027 @@
+//! Line-per-CIDR input parsing.
+//!
+//! Every non-empty line of input is expected to hold a single IPv4 or IPv6 CIDR, or an
+//! address range such as `10.0.0.5-10.0.0.200`, optionally followed by a comment
+//! starting with `#` or `;`. Ranges are decomposed into the minimal list of CIDRs
+//! covering them. Bare addresses are single hosts, and IPv4 networks may also be
+//! written with a dotted netmask or wildcard mask instead of a prefix length. Host bits
+//! set beyond the prefix length (as in `10.0.0.1/24`) are cleared unless
+//! [`HostBitsMode`] says otherwise. Lines that hold only a comment are ignored. The text
+//! of a trailing comment is kept as the line's annotation. Failures are reported as [`ParseError`]
+//! values that carry enough context (line, column, raw text, and a machine-readable
+//! [`ParseErrorKind`]) for callers to act on them without inspecting error strings.
+
+use std::fmt;
+use std::io::{self, BufRead};
+use std::net::{IpAddr, Ipv4Addr};
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+
+use crate::AnnotatedNet;
+use crate::setops::range_to_nets;
+
+/// Error describing why a line of input could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based number of the offending line.
+    pub line: usize,
+    /// 1-based column (in characters) at which the offending part of the line starts.
+    ///
+    /// I/O failures and missing columns are not tied to a position and report
+    /// column 0.
+    pub column: usize,
+    /// Raw text of the offending line, without the line terminator.
+    ///
+    /// For CSV input, the text of the selected field instead. Empty for I/O failures,
+    /// where the line could not be read, and the name or number of the column for
+    /// missing columns.
+    pub text: String,
+    /// What went wrong.
+    pub kind: ParseErrorKind,
+}
+
+/// The category of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The address part of the line is not a valid IP address of an accepted family.
+    InvalidAddress,
+    /// The prefix length or dotted mask is malformed, or the prefix length is too long
+    /// for the address family.
+    InvalidPrefix,
+    /// The address has bits set beyond the prefix length (e.g. `10.0.0.1/24`).
+    HostBitsSet,
+    /// An address range ends before it starts, or mixes address families.
+    InvalidRange,
+    /// A dotted mask is neither a netmask nor a wildcard mask because its bits are not
+    /// contiguous (e.g. `255.0.255.0`).
+    InvalidMask,
+    /// The all-zero mask `0.0.0.0` follows an address after a space, where it could be
+    /// a `/0` netmask or a single-host wildcard mask.
+    AmbiguousMask,
+    /// The CSV header has no column of the requested name, or a record has no field
+    /// at the requested position.
+    MissingColumn,
+    /// The underlying reader failed.
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::InvalidAddress => write!(f, "invalid IP address syntax"),
+            ParseErrorKind::InvalidPrefix => write!(f, "invalid prefix length"),
+            ParseErrorKind::HostBitsSet => write!(f, "host bits set beyond the prefix length"),
+            ParseErrorKind::InvalidRange => write!(f, "invalid address range"),
+            ParseErrorKind::InvalidMask => write!(f, "non-contiguous netmask or wildcard mask"),
+            ParseErrorKind::AmbiguousMask => {
+                write!(f, "ambiguous mask 0.0.0.0 (netmask /0 or host wildcard)")
+            }
+            ParseErrorKind::MissingColumn => write!(f, "missing CSV column"),
+            ParseErrorKind::Io(kind) => write!(f, "failed to read input: {kind}"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::Io(_) => return write!(f, "Line {}: {}", self.line, self.kind),
+            ParseErrorKind::MissingColumn => {
+                return write!(f, "Line {}: {} {:?}", self.line, self.kind, self.text);
+            }
+            _ => {}
+        }
+
+        write!(
+            f,
+            "Line {}: {} (column {}: {:?})",
+            self.line, self.kind, self.column, self.text
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Controls how the parser reacts to invalid lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Stop at the first invalid line and report only that line.
+    #[default]
+    FailFast,
+    /// Keep parsing after invalid lines and report every one of them.
+    CollectAll,
+    /// Skip invalid lines and return them alongside the valid networks instead of
+    /// failing.
+    Skip,
+}
+
+/// Controls how the parser treats CIDRs whose address has bits set beyond the prefix
+/// length, such as `10.0.0.1/24`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HostBitsMode {
+    /// Clear the host bits, so `10.0.0.1/24` is read as `10.0.0.0/24`.
+    #[default]
+    Normalize,
+    /// Report the line as a [`ParseErrorKind::HostBitsSet`] error, handled according to
+    /// the [`ErrorMode`].
+    Reject,
+    /// Clear the host bits like [`HostBitsMode::Normalize`], and record the line in
+    /// [`ParsedInput::normalized`].
+    Warn,
+}
+
+/// Result of [`parse_ip_nets_with_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedInput {
+    /// Networks parsed from the valid lines, in input order.
+    pub nets: Vec<IpNet>,
+    /// Trailing comment of the line each network came from, parallel to `nets`.
+    ///
+    /// `None` when the line had no comment or an empty one.
+    pub annotations: Vec<Option<String>>,
+    /// Lines rejected under [`ErrorMode::Skip`], in input order.
+    ///
+    /// Always empty in the other modes, which fail instead.
+    pub rejected: Vec<ParseError>,
+    /// Lines whose host bits were cleared under [`HostBitsMode::Warn`], in input order,
+    /// as [`ParseErrorKind::HostBitsSet`] errors.
+    ///
+    /// Always empty in the other modes.
+    pub normalized: Vec<ParseError>,
+}
+
+impl ParsedInput {
+    /// Pairs every parsed network with its annotation, ready for
+    /// [`merge_annotated_ip_nets`](crate::merge_annotated_ip_nets).
+    pub fn into_annotated(self) -> Vec<AnnotatedNet> {
+        self.nets
+            .into_iter()
+            .zip(self.annotations)
+            .map(|(net, annotation)| AnnotatedNet {
+                net,
+                annotations: annotation.into_iter().collect(),
+                sources: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+/// Networks read from structured input such as CSV, where each network can carry
+/// several annotations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedRecords {
+    /// Networks parsed from the valid records, in input order, with their annotations.
+    pub nets: Vec<AnnotatedNet>,
+    /// Records rejected under [`ErrorMode::Skip`], in input order.
+    ///
+    /// Always empty in the other modes, which fail instead.
+    pub rejected: Vec<ParseError>,
+    /// Records whose host bits were cleared under [`HostBitsMode::Warn`], in input
+    /// order.
+    pub normalized: Vec<ParseError>,
+}
+
+/// Options for [`parse_ip_nets_with_options`] and [`parse_csv_ip_nets`](crate::parse_csv_ip_nets).
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// How invalid lines are handled.
+    pub error_mode: ErrorMode,
+    /// How CIDRs with host bits set are handled.
+    pub host_bits: HostBitsMode,
+}
+
+/// Parse IPv4 CIDRs from the provided buffered reader.
+///
+/// Empty lines and comments are ignored. Besides CIDRs, each line may hold:
+///
+/// - a bare address such as `192.0.2.7`, read as a /32 host;
+/// - a dotted netmask, after a slash or a space (`10.0.0.0/255.255.255.0` or
+///   `10.0.0.0 255.255.255.0`);
+/// - a Cisco-style wildcard mask after a space (`10.0.0.0 0.0.0.255`); the all-zero
+///   mask is ambiguous there and rejected;
+/// - an address range such as `10.0.0.5-10.0.0.200` (with or without spaces around the
+///   dash), decomposed into the minimal list of CIDRs covering it.
+///
+/// Host bits set beyond the prefix length are cleared, so `10.0.0.1/24` is read as
+/// `10.0.0.0/24`. Invalid lines, including IPv6 ones and masks whose bits are not
+/// contiguous, return a [`ParseError`] describing the first offending line.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::parse_ipv4_nets;
+/// use std::io::Cursor;
+///
+/// let nets = parse_ipv4_nets(Cursor::new("10.0.0.0/24\n10.0.1.1 - 10.0.1.6\n")).unwrap();
+/// let printed: Vec<String> = nets.iter().map(|net| net.to_string()).collect();
+///
+/// assert_eq!(
+///     printed,
+///     ["10.0.0.0/24", "10.0.1.1/32", "10.0.1.2/31", "10.0.1.4/31", "10.0.1.6/32"]
+/// );
+/// ```
+pub fn parse_ipv4_nets<R: BufRead>(reader: R) -> Result<Vec<Ipv4Net>, ParseError> {
+    let mut nets = Vec::new();
+    parse_lines(reader, ErrorMode::FailFast, |_, line| {
+        let mut parsed = parse_line(line)?;
+        clear_host_bits(&mut parsed);
+        for net in parsed {
+            match net {
+                IpNet::V4(net) => nets.push(net),
+                IpNet::V6(_) => {
+                    return Err((leading_whitespace(line), ParseErrorKind::InvalidAddress));
+                }
+            }
+        }
+        Ok(())
+    })
+    .map_err(first_error)?;
+
+    Ok(nets)
+}
+
+/// Parse IPv4 and IPv6 CIDRs from the provided buffered reader.
+///
+/// The address family is detected independently for each line, so a single input
+/// may mix both families. Empty lines and comments are ignored, and bare addresses
+/// (/32 or /128 hosts), dotted masks and address ranges are accepted, and host bits are
+/// cleared, as in [`parse_ipv4_nets`]. Invalid lines return a [`ParseError`] describing
+/// the first offending line.
+pub fn parse_ip_nets<R: BufRead>(reader: R) -> Result<Vec<IpNet>, ParseError> {
+    parse_ip_nets_with_options(reader, &ParseOptions::default())
+        .map(|parsed| parsed.nets)
+        .map_err(first_error)
+}
+
+/// Parse IPv4 and IPv6 CIDRs with explicit [`ParseOptions`].
+///
+/// With [`ErrorMode::CollectAll`], parsing continues past invalid lines and every
+/// one of them is returned, in input order. With [`ErrorMode::Skip`], invalid lines
+/// are returned in [`ParsedInput::rejected`] next to the valid networks. An I/O
+/// failure always ends parsing, since nothing after it can be read reliably.
+///
+/// [`ParseOptions::host_bits`] decides whether a CIDR such as `10.0.0.1/24` is
+/// normalized to `10.0.0.0/24` (the default) or treated as an invalid line.
+///
+/// # Errors
+///
+/// Returns the collected errors if any line failed to parse. In
+/// [`ErrorMode::FailFast`] mode the vector holds exactly one error; in
+/// [`ErrorMode::Skip`] mode only an I/O failure is returned as an error.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{
+///     parse_ip_nets_with_options, ErrorMode, HostBitsMode, ParseErrorKind, ParseOptions,
+/// };
+/// use std::io::Cursor;
+///
+/// let options = ParseOptions {
+///     error_mode: ErrorMode::CollectAll,
+///     ..ParseOptions::default()
+/// };
+/// let input = Cursor::new("10.0.0.0/24\n10.0.0.0/33\nbogus\n");
+/// let errors = parse_ip_nets_with_options(input, &options).unwrap_err();
+///
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!((errors[0].line, errors[0].kind), (2, ParseErrorKind::InvalidPrefix));
+/// assert_eq!((errors[1].line, errors[1].kind), (3, ParseErrorKind::InvalidAddress));
+///
+/// let options = ParseOptions {
+///     error_mode: ErrorMode::Skip,
+///     ..ParseOptions::default()
+/// };
+/// let input = Cursor::new("10.0.0.0/24\n10.0.0.0/33\nbogus\n");
+/// let parsed = parse_ip_nets_with_options(input, &options).unwrap();
+///
+/// assert_eq!(parsed.nets.len(), 1);
+/// assert_eq!(parsed.rejected.len(), 2);
+///
+/// let options = ParseOptions {
+///     host_bits: HostBitsMode::Reject,
+///     ..ParseOptions::default()
+/// };
+/// let errors = parse_ip_nets_with_options(Cursor::new("10.0.0.1/24\n"), &options).unwrap_err();
+///
+/// assert_eq!(errors[0].kind, ParseErrorKind::HostBitsSet);
+/// ```
+pub fn parse_ip_nets_with_options<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<ParsedInput, Vec<ParseError>> {
+    let mut nets = Vec::new();
+    let mut annotations = Vec::new();
+    let mut normalized = Vec::new();
+    let rejected = parse_lines(reader, options.error_mode, |line_no, line| {
+        let (parsed, cleared) = parse_entry(line, options.host_bits)?;
+        if cleared {
+            normalized.push(host_bits_warning(line_no, line));
+        }
+        let annotation = split_comment(line).1.map(str::to_string);
+        annotations.extend(std::iter::repeat_n(annotation, parsed.len()));
+        nets.extend(parsed);
+        Ok(())
+    })?;
+
+    Ok(ParsedInput {
+        nets,
+        annotations,
+        rejected,
+        normalized,
+    })
+}
+
+/// Line-level error: the byte offset of the offending part of the line plus the
+/// error kind.
+pub(crate) type LineError = (usize, ParseErrorKind);
+
+/// Feeds every line of `reader` to `handle`, together with its 1-based line number,
+/// collecting errors according to `error_mode`.
+///
+/// Line errors are converted into a [`ParseError`] that carries the line number,
+/// column, and raw text. Read failures are reported as [`ParseErrorKind::Io`] and
+/// always stop the iteration.
+///
+/// Returns the skipped lines in [`ErrorMode::Skip`] mode (empty otherwise), or the
+/// errors that made parsing fail.
+fn parse_lines<R, F>(
+    reader: R,
+    error_mode: ErrorMode,
+    mut handle: F,
+) -> Result<Vec<ParseError>, Vec<ParseError>>
+where
+    R: BufRead,
+    F: FnMut(usize, &str) -> Result<(), LineError>,
+{
+    let mut errors = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let raw = match line {
+            Ok(raw) => raw,
+            Err(err) => {
+                let io_error = ParseError {
+                    line: idx + 1,
+                    column: 0,
+                    text: String::new(),
+                    kind: ParseErrorKind::Io(err.kind()),
+                };
+                // Skipped lines are not failures, so only the I/O error is reported
+                if error_mode == ErrorMode::Skip {
+                    errors.clear();
+                }
+                errors.push(io_error);
+                return Err(errors);
+            }
+        };
+
+        if let Err(err) = handle(idx + 1, &raw) {
+            errors.push(line_error(idx + 1, &raw, err));
+            if error_mode == ErrorMode::FailFast {
+                return Err(errors);
+            }
+        }
+    }
+
+    if error_mode == ErrorMode::Skip || errors.is_empty() {
+        Ok(errors)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the networks of a single line (or field) and applies the host bits policy.
+///
+/// Returns the networks, and whether host bits were cleared and should be reported
+/// under [`HostBitsMode::Warn`].
+pub(crate) fn parse_entry(
+    line: &str,
+    host_bits: HostBitsMode,
+) -> Result<(Vec<IpNet>, bool), LineError> {
+    let mut parsed = parse_line(line)?;
+    let cleared = clear_host_bits(&mut parsed);
+    if cleared && host_bits == HostBitsMode::Reject {
+        return Err((leading_whitespace(line), ParseErrorKind::HostBitsSet));
+    }
+    Ok((parsed, cleared && host_bits == HostBitsMode::Warn))
+}
+
+/// Builds the [`ParseErrorKind::HostBitsSet`] entry recorded for line `line` under
+/// [`HostBitsMode::Warn`].
+pub(crate) fn host_bits_warning(line: usize, raw: &str) -> ParseError {
+    line_error(
+        line,
+        raw,
+        (leading_whitespace(raw), ParseErrorKind::HostBitsSet),
+    )
+}
+
+/// Parses a single input line into the networks it describes.
+///
+/// Returns no networks for lines that carry none, such as blank lines and comment-only
+/// lines, one for a CIDR, and as many as needed to cover an address range.
+fn parse_line(line: &str) -> Result<Vec<IpNet>, LineError> {
+    let (content, _) = split_comment(line);
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let offset = leading_whitespace(line);
+    // `-` never appears in a CIDR, so it always separates the ends of a range
+    match trimmed.find('-') {
+        Some(dash) => parse_range(trimmed, dash),
+        None => parse_cidr(trimmed).map(|net| vec![net]),
+    }
+    .map_err(|(pos, kind)| (offset + pos, kind))
+}
+
+/// Clears the host bits of every network in `nets`.
+///
+/// Returns whether any network had host bits set.
+fn clear_host_bits(nets: &mut [IpNet]) -> bool {
+    let mut cleared = false;
+    for net in nets {
+        if net.addr() != net.network() {
+            *net = net.trunc();
+            cleared = true;
+        }
+    }
+    cleared
+}
+
+/// Parses an address range token such as `10.0.0.5-10.0.0.200` or
+/// `10.0.0.5 - 10.0.0.200`, whose dash is at byte `dash`, into the minimal list of CIDRs
+/// covering it.
+///
+/// On failure, returns the byte offset within `token` at which the problem starts.
+fn parse_range(token: &str, dash: usize) -> Result<Vec<IpNet>, LineError> {
+    let (start_str, end_str) = (&token[..dash], &token[dash + 1..]);
+    let start: IpAddr = start_str
+        .trim_end()
+        .parse()
+        .map_err(|_| (0, ParseErrorKind::InvalidAddress))?;
+    let end_offset = dash + 1 + leading_whitespace(end_str);
+    let end: IpAddr = end_str
+        .trim()
+        .parse()
+        .map_err(|_| (end_offset, ParseErrorKind::InvalidAddress))?;
+
+    let mut nets = Vec::new();
+    match (start, end) {
+        (IpAddr::V4(start), IpAddr::V4(end)) if start <= end => {
+            let mut v4: Vec<Ipv4Net> = Vec::new();
+            range_to_nets(u32::from(start).into(), u32::from(end).into(), &mut v4);
+            nets.extend(v4.into_iter().map(IpNet::V4));
+        }
+        (IpAddr::V6(start), IpAddr::V6(end)) if start <= end => {
+            let mut v6: Vec<Ipv6Net> = Vec::new();
+            range_to_nets(start.into(), end.into(), &mut v6);
+            nets.extend(v6.into_iter().map(IpNet::V6));
+        }
+        _ => return Err((end_offset, ParseErrorKind::InvalidRange)),
+    }
+    Ok(nets)
+}
+
+/// Parses a network token: a CIDR such as `10.0.0.0/24` or `2001:db8::/32`, a bare
+/// address (a single host), or an IPv4 address with a dotted netmask
+/// (`10.0.0.0/255.255.255.0` or `10.0.0.0 255.255.255.0`) or, after a space only, a
+/// wildcard mask (`10.0.0.0 0.0.0.255`).
+///
+/// Only a dotted mask may follow a space: a decimal prefix length there (`10.0.0.0 24`)
+/// is rejected, since it is more likely a stray column than part of the network.
+///
+/// On failure, returns the byte offset within `token` at which the problem starts.
+fn parse_cidr(token: &str) -> Result<IpNet, LineError> {
+    let (addr_str, mask, after_slash) = match token.split_once('/') {
+        Some((addr_str, prefix_str)) => (addr_str, Some((addr_str.len() + 1, prefix_str)), true),
+        None => match token.split_once(char::is_whitespace) {
+            Some((addr_str, rest)) => {
+                let mask_str = rest.trim_start();
+                (
+                    addr_str,
+                    Some((token.len() - mask_str.len(), mask_str)),
+                    false,
+                )
+            }
+            None => (token, None, false),
+        },
+    };
+
+    let addr: IpAddr = addr_str
+        .parse()
+        .map_err(|_| (0, ParseErrorKind::InvalidAddress))?;
+
+    let prefix_len = match mask {
+        None => match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        },
+        Some((offset, mask_str)) if mask_str.contains('.') => {
+            let mask = mask_str
+                .parse()
+                .map_err(|_| (offset, ParseErrorKind::InvalidPrefix))?;
+            if addr.is_ipv6() {
+                return Err((offset, ParseErrorKind::InvalidPrefix));
+            }
+            let prefix_len = if after_slash {
+                netmask_prefix_len(mask).ok_or(ParseErrorKind::InvalidMask)
+            } else {
+                mask_prefix_len(mask)
+            };
+            prefix_len.map_err(|kind| (offset, kind))?
+        }
+        Some((offset, prefix_str)) => {
+            if !after_slash
+                || prefix_str.is_empty()
+                || !prefix_str.bytes().all(|b| b.is_ascii_digit())
+            {
+                return Err((offset, ParseErrorKind::InvalidPrefix));
+            }
+            prefix_str
+                .parse()
+                .map_err(|_| (offset, ParseErrorKind::InvalidPrefix))?
+        }
+    };
+
+    IpNet::new(addr, prefix_len).map_err(|_| {
+        let offset = mask.map_or(token.len(), |(offset, _)| offset);
+        (offset, ParseErrorKind::InvalidPrefix)
+    })
+}
+
+/// Converts a dotted IPv4 netmask (`255.255.255.0`) into a prefix length.
+///
+/// Returns `None` if the set bits of the mask are not contiguous from the top.
+fn netmask_prefix_len(mask: Ipv4Addr) -> Option<u8> {
+    let bits = u32::from(mask);
+    (bits.leading_ones() + bits.trailing_zeros() == 32)
+        .then(|| u8::try_from(bits.leading_ones()).expect("at most 32"))
+}
+
+/// Converts a dotted IPv4 netmask (`255.255.255.0`) or Cisco-style wildcard mask
+/// (`0.0.0.255`) into a prefix length.
+///
+/// `0.0.0.0` is both a `/0` netmask and a single-host wildcard, so it is reported as
+/// [`ParseErrorKind::AmbiguousMask`] rather than guessed. `255.255.255.255` is read as
+/// a netmask.
+fn mask_prefix_len(mask: Ipv4Addr) -> Result<u8, ParseErrorKind> {
+    if mask.is_unspecified() {
+        return Err(ParseErrorKind::AmbiguousMask);
+    }
+    let bits = u32::from(mask);
+    netmask_prefix_len(mask)
+        .or_else(|| {
+            (bits.leading_zeros() + bits.trailing_ones() == 32)
+                .then(|| u8::try_from(bits.leading_zeros()).expect("at most 32"))
+        })
+        .ok_or(ParseErrorKind::InvalidMask)
+}
+
+/// Splits a line into its content and the text of its trailing comment.
+///
+/// A comment starts at the first `#` or `;`, neither of which can appear in an IPv4 or
+/// IPv6 CIDR or range. The comment text is trimmed and reported as `None` when empty.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    match line.find(['#', ';']) {
+        Some(pos) => {
+            let comment = line[pos + 1..].trim();
+            (&line[..pos], (!comment.is_empty()).then_some(comment))
+        }
+        None => (line, None),
+    }
+}
+
+/// Returns the byte length of the leading whitespace of `line`.
+fn leading_whitespace(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Builds a [`ParseError`] for an error at byte `offset` of line `line`.
+pub(crate) fn line_error(line: usize, raw: &str, (offset, kind): LineError) -> ParseError {
+    ParseError {
+        line,
+        column: raw[..offset].chars().count() + 1,
+        text: raw.to_string(),
+        kind,
+    }
+}
+
+/// Extracts the first error from a non-empty list of collected errors.
+fn first_error(mut errors: Vec<ParseError>) -> ParseError {
+    errors.swap_remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_error_reports_line_column_and_text() {
+        let input = "10.0.0.0/24\n  999.0.0.0/24\n";
+        let err = parse_ip_nets(Cursor::new(input)).unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError {
+                line: 2,
+                column: 3,
+                text: "  999.0.0.0/24".to_string(),
+                kind: ParseErrorKind::InvalidAddress,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Line 2: invalid IP address syntax (column 3: \"  999.0.0.0/24\")"
+        );
+    }
+
+    #[test]
+    fn parse_error_points_at_prefix() {
+        let err = parse_ip_nets(Cursor::new("10.0.0.0/33")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidPrefix));
+
+        let err = parse_ip_nets(Cursor::new("2001:db8::/129")).unwrap_err();
+        assert_eq!((err.column, err.kind), (12, ParseErrorKind::InvalidPrefix));
+
+        let err = parse_ip_nets(Cursor::new("10.0.0.0/+8")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidPrefix));
+
+        let err = parse_ip_nets(Cursor::new("10.0.0.0/")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidPrefix));
+    }
+
+    #[test]
+    fn bare_addresses_are_single_hosts() {
+        let nets = parse_ip_nets(Cursor::new("192.0.2.7\n  2001:db8::1 # router\n")).unwrap();
+        assert_eq!(
+            nets,
+            vec![
+                "192.0.2.7/32".parse::<IpNet>().unwrap(),
+                "2001:db8::1/128".parse::<IpNet>().unwrap(),
+            ]
+        );
+
+        let err = parse_ip_nets(Cursor::new("not-an-address")).unwrap_err();
+        assert_eq!((err.column, err.kind), (1, ParseErrorKind::InvalidAddress));
+    }
+
+    #[test]
+    fn dotted_netmasks_and_wildcards_become_prefix_lengths() {
+        let input = "10.0.0.0 255.255.255.0\n10.1.0.0/255.255.0.0\n10.2.0.0\t0.0.0.255\n\
+                     10.3.0.0 0.0.255.255\n192.0.2.7 0.0.0.1\n0.0.0.0/0.0.0.0\n\
+                     192.0.2.8 255.255.255.255\n";
+        let nets = parse_ipv4_nets(Cursor::new(input)).unwrap();
+
+        let printed: Vec<String> = nets.iter().map(|net| net.to_string()).collect();
+        assert_eq!(
+            printed,
+            [
+                "10.0.0.0/24",
+                "10.1.0.0/16",
+                "10.2.0.0/24",
+                "10.3.0.0/16",
+                "192.0.2.6/31",
+                "0.0.0.0/0",
+                "192.0.2.8/32",
+            ]
+        );
+    }
+
+    #[test]
+    fn non_contiguous_masks_are_rejected() {
+        let err = parse_ipv4_nets(Cursor::new("10.0.0.0 255.0.255.0")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidMask));
+        assert_eq!(
+            err.to_string(),
+            "Line 1: non-contiguous netmask or wildcard mask (column 10: \"10.0.0.0 255.0.255.0\")"
+        );
+
+        let err = parse_ipv4_nets(Cursor::new("10.0.0.0/0.255.0.255")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidMask));
+
+        let err = parse_ip_nets(Cursor::new("10.0.0.0 255.255.0")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidPrefix));
+
+        let err = parse_ip_nets(Cursor::new("2001:db8:: 255.255.0.0")).unwrap_err();
+        assert_eq!((err.column, err.kind), (12, ParseErrorKind::InvalidPrefix));
+    }
+
+    #[test]
+    fn wildcard_masks_only_follow_a_space() {
+        let err = parse_ipv4_nets(Cursor::new("10.0.0.0/0.0.0.255")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidMask));
+
+        // After a slash, the all-zero mask is always the /0 netmask
+        let nets = parse_ipv4_nets(Cursor::new("10.0.0.0/0.0.0.0")).unwrap();
+        assert_eq!(nets, vec!["0.0.0.0/0".parse().unwrap()]);
+
+        for (line, column) in [("192.0.2.7 0.0.0.0", 11), ("0.0.0.0 0.0.0.0", 9)] {
+            let err = parse_ipv4_nets(Cursor::new(line)).unwrap_err();
+            assert_eq!(
+                (err.column, err.kind),
+                (column, ParseErrorKind::AmbiguousMask)
+            );
+        }
+    }
+
+    #[test]
+    fn decimal_prefix_after_a_space_is_rejected() {
+        for (line, column) in [
+            ("10.0.0.0 24", 10),
+            ("192.0.2.7 1", 11),
+            ("10.0.0.0 0", 10),
+            ("2001:db8:: 64", 12),
+        ] {
+            let err = parse_ip_nets(Cursor::new(line)).unwrap_err();
+            assert_eq!(
+                (err.column, err.kind),
+                (column, ParseErrorKind::InvalidPrefix),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_ipv4_nets_reports_ipv6_as_invalid_address() {
+        let err = parse_ipv4_nets(Cursor::new("10.0.0.0/8\n\t2001:db8::/32")).unwrap_err();
+        assert_eq!(
+            (err.line, err.column, err.kind),
+            (2, 2, ParseErrorKind::InvalidAddress)
+        );
+    }
+
+    #[test]
+    fn fail_fast_reports_only_first_error() {
+        let input = "bogus\n10.0.0.0/24\nalso-bogus";
+        let errors =
+            parse_ip_nets_with_options(Cursor::new(input), &ParseOptions::default()).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+    }
+
+    #[test]
+    fn collect_all_reports_every_error_in_order() {
+        let options = ParseOptions {
+            error_mode: ErrorMode::CollectAll,
+            ..ParseOptions::default()
+        };
+        let input = "bogus\n10.0.0.0/24\n\n10.0.0.0/40\n2001:db8::/32\nalso-bogus";
+        let errors = parse_ip_nets_with_options(Cursor::new(input), &options).unwrap_err();
+
+        let summary: Vec<(usize, ParseErrorKind)> =
+            errors.iter().map(|err| (err.line, err.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, ParseErrorKind::InvalidAddress),
+                (4, ParseErrorKind::InvalidPrefix),
+                (6, ParseErrorKind::InvalidAddress),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_all_succeeds_on_valid_input() {
+        let options = ParseOptions {
+            error_mode: ErrorMode::CollectAll,
+            ..ParseOptions::default()
+        };
+        let parsed =
+            parse_ip_nets_with_options(Cursor::new("10.0.0.0/24\n2001:db8::/32"), &options)
+                .unwrap();
+
+        assert_eq!(parsed.nets.len(), 2);
+        assert!(parsed.rejected.is_empty());
+    }
+
+    #[test]
+    fn skip_returns_valid_networks_and_rejected_lines() {
+        let options = ParseOptions {
+            error_mode: ErrorMode::Skip,
+            ..ParseOptions::default()
+        };
+        let input = "10.0.0.0/24\njunk\n\n2001:db8::/32\n10.0.0.0/99\n192.168.0.0/16";
+        let parsed = parse_ip_nets_with_options(Cursor::new(input), &options).unwrap();
+
+        assert_eq!(
+            parsed.nets,
+            vec![
+                "10.0.0.0/24".parse::<IpNet>().unwrap(),
+                "2001:db8::/32".parse::<IpNet>().unwrap(),
+                "192.168.0.0/16".parse::<IpNet>().unwrap(),
+            ]
+        );
+        let rejected: Vec<(usize, &str)> = parsed
+            .rejected
+            .iter()
+            .map(|err| (err.line, err.text.as_str()))
+            .collect();
+        assert_eq!(rejected, vec![(2, "junk"), (5, "10.0.0.0/99")]);
+    }
+
+    #[test]
+    fn host_bits_are_cleared_by_default() {
+        let nets = parse_ip_nets(Cursor::new("10.0.0.1/24\n2001:db8::1/64\n")).unwrap();
+        assert_eq!(
+            nets,
+            vec![
+                "10.0.0.0/24".parse::<IpNet>().unwrap(),
+                "2001:db8::/64".parse::<IpNet>().unwrap(),
+            ]
+        );
+
+        let nets = parse_ipv4_nets(Cursor::new("192.0.2.77 255.255.255.192")).unwrap();
+        assert_eq!(nets, vec!["192.0.2.64/26".parse::<Ipv4Net>().unwrap()]);
+    }
+
+    #[test]
+    fn host_bits_can_be_rejected_or_reported() {
+        let input = "10.0.0.0/24\n  10.0.1.1/24 # gw\n10.0.2.0/24";
+        let options = ParseOptions {
+            error_mode: ErrorMode::CollectAll,
+            host_bits: HostBitsMode::Reject,
+        };
+        let errors = parse_ip_nets_with_options(Cursor::new(input), &options).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            (errors[0].line, errors[0].column, errors[0].kind),
+            (2, 3, ParseErrorKind::HostBitsSet)
+        );
+
+        let options = ParseOptions {
+            host_bits: HostBitsMode::Warn,
+            ..ParseOptions::default()
+        };
+        let parsed = parse_ip_nets_with_options(Cursor::new(input), &options).unwrap();
+        assert_eq!(parsed.nets[1], "10.0.1.0/24".parse::<IpNet>().unwrap());
+        assert_eq!(parsed.normalized.len(), 1);
+        assert_eq!(parsed.normalized[0].line, 2);
+        assert!(parsed.rejected.is_empty());
+    }
+
+    #[test]
+    fn skip_still_fails_on_io_error() {
+        let options = ParseOptions {
+            error_mode: ErrorMode::Skip,
+            ..ParseOptions::default()
+        };
+        let input = Cursor::new(b"junk\n10.0.0.0/24\n\xff\n".to_vec());
+        let errors = parse_ip_nets_with_options(input, &options).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::Io(io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn io_failure_is_reported_with_kind() {
+        // Invalid UTF-8 makes `BufRead::lines` fail with `InvalidData`
+        let input = Cursor::new(b"10.0.0.0/24\n\xff\xfe\n".to_vec());
+        let options = ParseOptions {
+            error_mode: ErrorMode::CollectAll,
+            ..ParseOptions::default()
+        };
+        let errors = parse_ip_nets_with_options(input, &options).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::Io(io::ErrorKind::InvalidData)
+        );
+        assert!(
+            errors[0]
+                .to_string()
+                .starts_with("Line 2: failed to read input")
+        );
+    }
+
+    #[test]
+    fn parse_error_is_std_error() {
+        let err: Box<dyn std::error::Error> =
+            Box::new(parse_ip_nets(Cursor::new("bogus")).unwrap_err());
+        assert!(err.to_string().starts_with("Line 1:"));
+    }
+
+    #[test]
+    fn comments_are_stripped_and_kept_as_annotations() {
+        let input = "# header\n10.0.0.0/24  # prod-eu\n; legacy section\n10.0.1.0/24;prod-us\n2001:db8::/32 #\n";
+        let parsed =
+            parse_ip_nets_with_options(Cursor::new(input), &ParseOptions::default()).unwrap();
+
+        assert_eq!(
+            parsed.nets,
+            vec![
+                "10.0.0.0/24".parse::<IpNet>().unwrap(),
+                "10.0.1.0/24".parse::<IpNet>().unwrap(),
+                "2001:db8::/32".parse::<IpNet>().unwrap(),
+            ]
+        );
+        assert_eq!(
+            parsed.annotations,
+            vec![
+                Some("prod-eu".to_string()),
+                Some("prod-us".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_ipv4_nets_strips_comments() {
+        let nets =
+            parse_ipv4_nets(Cursor::new("10.0.0.0/24 # office\n   # indented comment\n")).unwrap();
+        assert_eq!(nets, vec!["10.0.0.0/24".parse::<Ipv4Net>().unwrap()]);
+    }
+
+    #[test]
+    fn comment_does_not_hide_invalid_cidr() {
+        let err = parse_ip_nets(Cursor::new("10.0.0.0/33 # typo")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidPrefix));
+        assert_eq!(err.text, "10.0.0.0/33 # typo");
+    }
+
+    #[test]
+    fn into_annotated_pairs_networks_with_annotations() {
+        let parsed = parse_ip_nets_with_options(
+            Cursor::new("10.0.0.0/24 # a\n10.0.1.0/24\n"),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed.into_annotated(),
+            vec![
+                AnnotatedNet {
+                    net: "10.0.0.0/24".parse().unwrap(),
+                    annotations: vec!["a".to_string()],
+                    sources: vec![],
+                },
+                AnnotatedNet {
+                    net: "10.0.1.0/24".parse().unwrap(),
+                    annotations: vec![],
+                    sources: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_are_decomposed_into_minimal_cidrs() {
+        let input = "10.0.0.5-10.0.0.200 # vendor\n10.1.0.0 - 10.1.1.255\n2001:db8::-2001:db8::3\n";
+        let parsed =
+            parse_ip_nets_with_options(Cursor::new(input), &ParseOptions::default()).unwrap();
+
+        let printed: Vec<String> = parsed.nets.iter().map(|net| net.to_string()).collect();
+        assert_eq!(
+            printed,
+            [
+                "10.0.0.5/32",
+                "10.0.0.6/31",
+                "10.0.0.8/29",
+                "10.0.0.16/28",
+                "10.0.0.32/27",
+                "10.0.0.64/26",
+                "10.0.0.128/26",
+                "10.0.0.192/29",
+                "10.0.0.200/32",
+                "10.1.0.0/23",
+                "2001:db8::/126",
+            ]
+        );
+        // Every piece of a range keeps the line's annotation
+        assert_eq!(parsed.annotations[0], Some("vendor".to_string()));
+        assert_eq!(parsed.annotations[8], Some("vendor".to_string()));
+        assert_eq!(parsed.annotations[9], None);
+    }
+
+    #[test]
+    fn invalid_ranges_point_at_the_offending_end() {
+        let err = parse_ip_nets(Cursor::new("10.0.0.9 - 10.0.0.1")).unwrap_err();
+        assert_eq!((err.column, err.kind), (12, ParseErrorKind::InvalidRange));
+
+        let err = parse_ip_nets(Cursor::new("10.0.0.1-2001:db8::")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidRange));
+
+        let err = parse_ip_nets(Cursor::new("10.0.0.1-10.0.0")).unwrap_err();
+        assert_eq!((err.column, err.kind), (10, ParseErrorKind::InvalidAddress));
+
+        let err = parse_ip_nets(Cursor::new("  10.0.0/8-10.0.0.1")).unwrap_err();
+        assert_eq!((err.column, err.kind), (3, ParseErrorKind::InvalidAddress));
+    }
+
+    #[test]
+    fn single_address_range_is_a_host_route() {
+        let nets = parse_ipv4_nets(Cursor::new("192.0.2.7-192.0.2.7")).unwrap();
+        assert_eq!(nets, vec!["192.0.2.7/32".parse::<Ipv4Net>().unwrap()]);
+
+        let nets = parse_ipv4_nets(Cursor::new("0.0.0.0-255.255.255.255")).unwrap();
+        assert_eq!(nets, vec!["0.0.0.0/0".parse::<Ipv4Net>().unwrap()]);
+    }
+}
@@ -0,0 +1,331 @@
+//! Router prefix-list generation.
+//!
+//! [`prefix_list`] renders a merged list as a prefix list for BGP route filtering, in
+//! Cisco IOS, Junos, BIRD or FRR syntax. Aggregation usually drops the more-specific
+//! routes a peer announces, so each entry can carry `ge`/`le` length qualifiers; with
+//! [`LengthBound::Absorbed`], the upper bound of every entry comes from the merge
+//! provenance, accepting routes down to the longest input network it absorbed.
+
+use std::fmt::Write;
+
+use ipnet::IpNet;
+
+use crate::provenance::provenance;
+
+/// Configuration language of the generated prefix list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RouterSyntax {
+    /// Cisco IOS `ip prefix-list` and `ipv6 prefix-list` commands.
+    #[default]
+    Cisco,
+    /// Junos `set policy-options` commands: a `prefix-list`, or a `route-filter-list`
+    /// when qualifiers are requested.
+    Junos,
+    /// BIRD `define` statements holding prefix sets, one per address family.
+    Bird,
+    /// FRR `ip prefix-list` and `ipv6 prefix-list` commands.
+    Frr,
+}
+
+/// Upper prefix length (`le`) of prefix-list entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthBound {
+    /// The same length for every entry.
+    Length(u8),
+    /// The length of the longest input network absorbed by each entry.
+    Absorbed,
+}
+
+/// Options for [`prefix_list`].
+#[derive(Debug, Clone)]
+pub struct PrefixListOptions {
+    /// Syntax to generate.
+    pub syntax: RouterSyntax,
+    /// Name of the prefix list (for BIRD, of the IPv4 set; the IPv6 set gets a `_v6`
+    /// suffix).
+    pub name: String,
+    /// Lower prefix length (`ge`) of every entry.
+    pub ge: Option<u8>,
+    /// Upper prefix length (`le`) of every entry.
+    pub le: Option<LengthBound>,
+    /// Sequence number of the first entry of each list (Cisco and FRR only).
+    pub sequence_start: u32,
+    /// Increment between sequence numbers (Cisco and FRR only).
+    pub sequence_step: u32,
+}
+
+impl Default for PrefixListOptions {
+    fn default() -> Self {
+        PrefixListOptions {
+            syntax: RouterSyntax::default(),
+            name: String::new(),
+            ge: None,
+            le: None,
+            sequence_start: 5,
+            sequence_step: 5,
+        }
+    }
+}
+
+/// A network with the length range it matches, if any.
+struct Entry {
+    net: IpNet,
+    ge: Option<u8>,
+    le: Option<u8>,
+}
+
+/// Renders `merged`, the result of merging `inputs`, as a prefix list.
+///
+/// Every merged network becomes one `permit` entry, IPv4 and IPv6 in separate lists
+/// where the syntax requires it. A qualifier is only attached to an entry when it is
+/// longer than the entry's prefix length and no longer than its address, so a bound
+/// meant for one family leaves the other alone; when both apply and the `le` bound is
+/// shorter than `ge`, it is raised to `ge`. An entry absorbing no longer input has no
+/// [`LengthBound::Absorbed`] bound.
+///
+/// Junos has no per-entry qualifiers on a `prefix-list`, so a `route-filter-list` is
+/// written instead when `ge` or `le` is set, with `exact`, `upto` or
+/// `prefix-length-range` match types. Junos and BIRD have no sequence numbers.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{merge_ip_nets, prefix_list, LengthBound, PrefixListOptions};
+/// use ipnet::IpNet;
+///
+/// let inputs: Vec<IpNet> = vec!["10.0.0.0/24".parse().unwrap(), "10.0.1.0/24".parse().unwrap()];
+/// let merged = merge_ip_nets(inputs.clone(), 0, 0);
+/// let options = PrefixListOptions {
+///     name: "CUSTOMER".to_string(),
+///     le: Some(LengthBound::Absorbed),
+///     ..PrefixListOptions::default()
+/// };
+///
+/// assert_eq!(
+///     prefix_list(&inputs, &merged, &options),
+///     "ip prefix-list CUSTOMER seq 5 permit 10.0.0.0/23 le 24\n"
+/// );
+/// ```
+pub fn prefix_list(inputs: &[IpNet], merged: &[IpNet], options: &PrefixListOptions) -> String {
+    let entries: Vec<Entry> = provenance(inputs, merged)
+        .into_iter()
+        .map(|merged_net| {
+            let net = merged_net.net;
+            let longest_absorbed = merged_net
+                .covered
+                .iter()
+                .filter(|input| net.contains(*input))
+                .map(IpNet::prefix_len)
+                .max();
+            let applies = |len: &u8| *len > net.prefix_len() && *len <= net.max_prefix_len();
+
+            let ge = options.ge.filter(applies);
+            let le = match options.le {
+                Some(LengthBound::Length(len)) => Some(len),
+                Some(LengthBound::Absorbed) => longest_absorbed,
+                None => None,
+            }
+            .filter(applies)
+            .map(|le| le.max(ge.unwrap_or(0)));
+            Entry { net, ge, le }
+        })
+        .collect();
+    let (v4, v6): (Vec<Entry>, Vec<Entry>) = entries
+        .into_iter()
+        .partition(|entry| matches!(entry.net, IpNet::V4(_)));
+
+    let mut out = String::new();
+    match options.syntax {
+        RouterSyntax::Cisco | RouterSyntax::Frr => {
+            for (command, entries) in [("ip", &v4), ("ipv6", &v6)] {
+                let mut seq = options.sequence_start;
+                for entry in entries {
+                    let _ = write!(
+                        out,
+                        "{command} prefix-list {} seq {seq} permit {}",
+                        options.name, entry.net
+                    );
+                    if let Some(ge) = entry.ge {
+                        let _ = write!(out, " ge {ge}");
+                    }
+                    if let Some(le) = entry.le {
+                        let _ = write!(out, " le {le}");
+                    }
+                    out.push('\n');
+                    seq = seq.saturating_add(options.sequence_step);
+                }
+            }
+        }
+        RouterSyntax::Junos if options.ge.is_none() && options.le.is_none() => {
+            for entry in v4.iter().chain(&v6) {
+                let _ = writeln!(
+                    out,
+                    "set policy-options prefix-list {} {}",
+                    options.name, entry.net
+                );
+            }
+        }
+        RouterSyntax::Junos => {
+            for entry in v4.iter().chain(&v6) {
+                let match_type = match (entry.ge, entry.le) {
+                    (None, None) => "exact".to_string(),
+                    (None, Some(le)) => format!("upto /{le}"),
+                    (Some(ge), le) => format!(
+                        "prefix-length-range /{ge}-/{}",
+                        le.unwrap_or(entry.net.max_prefix_len())
+                    ),
+                };
+                let _ = writeln!(
+                    out,
+                    "set policy-options route-filter-list {} {} {match_type}",
+                    options.name, entry.net
+                );
+            }
+        }
+        RouterSyntax::Bird => {
+            for (name, entries) in [
+                (options.name.clone(), &v4),
+                (format!("{}_v6", options.name), &v6),
+            ] {
+                // BIRD rejects an empty set, so a family without networks is left out
+                if entries.is_empty() {
+                    continue;
+                }
+                let items: Vec<String> = entries
+                    .iter()
+                    .map(|entry| match (entry.ge, entry.le) {
+                        (None, None) => format!("\t{}", entry.net),
+                        (ge, le) => format!(
+                            "\t{}{{{},{}}}",
+                            entry.net,
+                            ge.unwrap_or(entry.net.prefix_len()),
+                            le.unwrap_or(entry.net.max_prefix_len())
+                        ),
+                    })
+                    .collect();
+                let _ = writeln!(out, "define {name} = [\n{}\n];", items.join(",\n"));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge_ip_nets;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn render(inputs: &[&str], options: PrefixListOptions) -> String {
+        let inputs = nets(inputs);
+        let merged = merge_ip_nets(inputs.clone(), 0, 0);
+        prefix_list(&inputs, &merged, &options)
+    }
+
+    const INPUTS: &[&str] = &[
+        "10.0.0.0/24",
+        "10.0.1.0/25",
+        "10.0.1.128/25",
+        "192.0.2.0/24",
+        "2001:db8::/48",
+        "2001:db8:1::/48",
+    ];
+
+    fn options(syntax: RouterSyntax, le: Option<LengthBound>) -> PrefixListOptions {
+        PrefixListOptions {
+            syntax,
+            name: "PEER".to_string(),
+            le,
+            ..PrefixListOptions::default()
+        }
+    }
+
+    #[test]
+    fn cisco_numbers_each_family_and_bounds_by_absorbed_inputs() {
+        let cisco = render(
+            INPUTS,
+            options(RouterSyntax::Cisco, Some(LengthBound::Absorbed)),
+        );
+
+        assert_eq!(
+            cisco,
+            "ip prefix-list PEER seq 5 permit 10.0.0.0/23 le 25\n\
+             ip prefix-list PEER seq 10 permit 192.0.2.0/24\n\
+             ipv6 prefix-list PEER seq 5 permit 2001:db8::/47 le 48\n"
+        );
+
+        let frr = render(
+            INPUTS,
+            PrefixListOptions {
+                ge: Some(24),
+                sequence_start: 10,
+                sequence_step: 10,
+                ..options(RouterSyntax::Frr, Some(LengthBound::Length(28)))
+            },
+        );
+        assert_eq!(
+            frr,
+            "ip prefix-list PEER seq 10 permit 10.0.0.0/23 ge 24 le 28\n\
+             ip prefix-list PEER seq 20 permit 192.0.2.0/24 le 28\n\
+             ipv6 prefix-list PEER seq 10 permit 2001:db8::/47\n"
+        );
+    }
+
+    #[test]
+    fn junos_uses_route_filters_only_for_qualifiers() {
+        assert_eq!(
+            render(
+                &["10.0.0.0/24", "2001:db8::/32"],
+                options(RouterSyntax::Junos, None)
+            ),
+            "set policy-options prefix-list PEER 10.0.0.0/24\n\
+             set policy-options prefix-list PEER 2001:db8::/32\n"
+        );
+
+        let junos = render(
+            INPUTS,
+            options(RouterSyntax::Junos, Some(LengthBound::Absorbed)),
+        );
+        assert_eq!(
+            junos,
+            "set policy-options route-filter-list PEER 10.0.0.0/23 upto /25\n\
+             set policy-options route-filter-list PEER 192.0.2.0/24 exact\n\
+             set policy-options route-filter-list PEER 2001:db8::/47 upto /48\n"
+        );
+
+        let junos = render(
+            &["10.0.0.0/24", "10.0.1.0/24"],
+            PrefixListOptions {
+                ge: Some(24),
+                ..options(RouterSyntax::Junos, None)
+            },
+        );
+        assert_eq!(
+            junos,
+            "set policy-options route-filter-list PEER 10.0.0.0/23 prefix-length-range /24-/32\n"
+        );
+    }
+
+    #[test]
+    fn bird_defines_one_set_per_family() {
+        let bird = render(
+            INPUTS,
+            options(RouterSyntax::Bird, Some(LengthBound::Absorbed)),
+        );
+
+        assert_eq!(
+            bird,
+            "define PEER = [\n\
+             \t10.0.0.0/23{23,25},\n\
+             \t192.0.2.0/24\n\
+             ];\n\
+             define PEER_v6 = [\n\
+             \t2001:db8::/47{47,48}\n\
+             ];\n"
+        );
+        assert!(!render(&["10.0.0.0/8"], options(RouterSyntax::Bird, None)).contains("_v6"));
+    }
+}
@@ -0,0 +1,249 @@
+//! Merge provenance: which input networks produced each merged network.
+//!
+//! [`merge_with_provenance`] merges like [`merge_ip_nets`], and records for every
+//! output network the inputs it covers and how it came about. This lets callers
+//! explain each aggregation, e.g. for change review.
+
+use std::collections::HashSet;
+
+use ipnet::IpNet;
+use serde::Serialize;
+
+use crate::{attribute_inputs, ip_address_count, merge_ip_nets};
+
+/// How a merged network relates to the input networks it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeKind {
+    /// The network was an input and covers no other distinct input.
+    Unchanged,
+    /// The network is part of a larger input that an exclusion split up.
+    Split,
+    /// The network was an input; other inputs inside it were removed as redundant.
+    SubnetRemoval,
+    /// The network was not an input, but covers exactly the addresses of its inputs.
+    ExactMerge,
+    /// The network covers addresses that none of its inputs covered.
+    ToleranceMerge,
+}
+
+/// A merged network together with the inputs it was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedNet {
+    /// The merged network.
+    pub net: IpNet,
+    /// How the network was produced.
+    pub kind: MergeKind,
+    /// Indices into the input slice of every input this network overlaps, ascending.
+    ///
+    /// After a merge, these are the inputs the network covers. After an exclusion,
+    /// they may also include a larger input that the network is a part of.
+    pub inputs: Vec<usize>,
+    /// Distinct input networks this network overlaps, in input order.
+    pub covered: Vec<IpNet>,
+    /// Number of addresses in the network that none of its inputs covered.
+    ///
+    /// Non-zero only for [`MergeKind::ToleranceMerge`].
+    pub extra_addresses: u128,
+}
+
+/// Merges networks like [`merge_ip_nets`], recording the provenance of every output.
+///
+/// Returns one [`MergedNet`] per merged network, in the same order as
+/// [`merge_ip_nets`] would return them. Every input index appears in exactly one
+/// [`MergedNet::inputs`] list.
+///
+/// # Arguments
+///
+/// * `nets` - IPv4 and/or IPv6 networks to merge
+/// * `tolerance_v4` - Maximum number of extra IPv4 addresses allowed per merge
+/// * `tolerance_v6` - Maximum number of extra IPv6 addresses allowed per merge
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::{merge_with_provenance, MergeKind};
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = ["10.0.1.0/24", "10.0.0.0/24", "10.0.8.0/24"]
+///     .iter()
+///     .map(|s| s.parse().unwrap())
+///     .collect();
+/// let merged = merge_with_provenance(&nets, 0, 0);
+///
+/// assert_eq!(merged[0].net.to_string(), "10.0.0.0/23");
+/// assert_eq!(merged[0].kind, MergeKind::ExactMerge);
+/// assert_eq!(merged[0].inputs, vec![0, 1]);
+/// assert_eq!(merged[1].kind, MergeKind::Unchanged);
+/// assert_eq!(merged[1].inputs, vec![2]);
+/// ```
+pub fn merge_with_provenance(
+    nets: &[IpNet],
+    tolerance_v4: u64,
+    tolerance_v6: u128,
+) -> Vec<MergedNet> {
+    let merged = merge_ip_nets(nets.to_vec(), tolerance_v4, tolerance_v6);
+    provenance(nets, &merged)
+}
+
+/// Describes the provenance of `merged`, the result of merging `inputs`.
+///
+/// `merged` must be sorted and non-overlapping: the output of merging `inputs`,
+/// possibly with ranges excluded afterwards.
+pub(crate) fn provenance(inputs: &[IpNet], merged: &[IpNet]) -> Vec<MergedNet> {
+    merged
+        .iter()
+        .zip(attribute_inputs(inputs, merged))
+        .map(|(&net, indices)| {
+            let mut seen: HashSet<IpNet> = HashSet::with_capacity(indices.len());
+            let covered: Vec<IpNet> = indices
+                .iter()
+                .map(|&idx| inputs[idx])
+                .filter(|input| seen.insert(*input))
+                .collect();
+
+            // The lossless merge of the overlapping inputs is disjoint, and each of its
+            // networks either lies inside this network or contains it, so this counts
+            // the input addresses inside this network exactly once.
+            let input_addresses = merge_ip_nets(covered.clone(), 0, 0)
+                .iter()
+                .map(|input| ip_address_count(if input.contains(&net) { &net } else { input }))
+                .fold(0, u128::saturating_add);
+            let extra_addresses = ip_address_count(&net).saturating_sub(input_addresses);
+
+            let kind = if extra_addresses > 0 {
+                MergeKind::ToleranceMerge
+            } else if covered
+                .iter()
+                .any(|input| *input != net && input.contains(&net))
+            {
+                MergeKind::Split
+            } else if !covered.contains(&net) {
+                MergeKind::ExactMerge
+            } else if covered.len() > 1 {
+                MergeKind::SubnetRemoval
+            } else {
+                MergeKind::Unchanged
+            };
+
+            MergedNet {
+                net,
+                kind,
+                inputs: indices,
+                covered,
+                extra_addresses,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn provenance_classifies_each_kind_of_output() {
+        let input = nets(&[
+            "10.0.0.0/24",   // 0: exact merge with 1
+            "10.0.1.0/24",   // 1
+            "10.1.0.0/16",   // 2: absorbs 3
+            "10.1.2.0/24",   // 3
+            "10.2.0.0/24",   // 4: tolerance merge with 5
+            "10.2.2.0/24",   // 5
+            "192.0.2.0/24",  // 6: unchanged
+            "192.0.2.0/24",  // 7: duplicate of 6
+            "2001:db8::/48", // 8: unchanged
+        ]);
+
+        let merged = merge_with_provenance(&input, 512, 0);
+
+        let summary: Vec<(String, MergeKind, Vec<usize>)> = merged
+            .iter()
+            .map(|m| (m.net.to_string(), m.kind, m.inputs.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("10.0.0.0/23".to_string(), MergeKind::ExactMerge, vec![0, 1]),
+                (
+                    "10.1.0.0/16".to_string(),
+                    MergeKind::SubnetRemoval,
+                    vec![2, 3]
+                ),
+                (
+                    "10.2.0.0/22".to_string(),
+                    MergeKind::ToleranceMerge,
+                    vec![4, 5]
+                ),
+                ("192.0.2.0/24".to_string(), MergeKind::Unchanged, vec![6, 7]),
+                ("2001:db8::/48".to_string(), MergeKind::Unchanged, vec![8]),
+            ]
+        );
+        assert_eq!(merged[2].extra_addresses, 512);
+        assert_eq!(merged[3].covered, nets(&["192.0.2.0/24"]));
+    }
+
+    #[test]
+    fn provenance_exact_merge_may_also_absorb_subnets() {
+        let input = nets(&["10.0.0.0/24", "10.0.0.128/25", "10.0.1.0/24"]);
+
+        let merged = merge_with_provenance(&input, 0, 0);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].kind, MergeKind::ExactMerge);
+        assert_eq!(merged[0].inputs, vec![0, 1, 2]);
+        assert_eq!(merged[0].extra_addresses, 0);
+    }
+
+    #[test]
+    fn provenance_covers_every_input_once() {
+        let input = nets(&["10.0.3.0/24", "10.0.0.0/24", "10.0.2.0/24", "10.0.1.0/24"]);
+
+        let merged = merge_with_provenance(&input, 0, 0);
+
+        let mut all: Vec<usize> = merged.iter().flat_map(|m| m.inputs.clone()).collect();
+        all.sort();
+        assert_eq!(all, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn provenance_marks_pieces_of_excluded_inputs_as_split() {
+        let input = nets(&["10.0.0.0/22", "10.0.0.0/24", "10.0.8.0/24", "10.0.10.0/24"]);
+        let merged = crate::exclude_ip_nets(
+            crate::merge_ip_nets(input.clone(), 512, 0),
+            nets(&["10.0.1.0/24", "10.0.9.0/24"]),
+        );
+
+        let described = provenance(&input, &merged);
+
+        let summary: Vec<(String, MergeKind, Vec<usize>, u128)> = described
+            .iter()
+            .map(|m| {
+                (
+                    m.net.to_string(),
+                    m.kind,
+                    m.inputs.clone(),
+                    m.extra_addresses,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("10.0.0.0/24".to_string(), MergeKind::Split, vec![0, 1], 0),
+                ("10.0.2.0/23".to_string(), MergeKind::Split, vec![0], 0),
+                ("10.0.8.0/24".to_string(), MergeKind::Unchanged, vec![2], 0),
+                (
+                    "10.0.10.0/23".to_string(),
+                    MergeKind::ToleranceMerge,
+                    vec![3],
+                    256
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Structured description of a merge result.
+//!
+//! A [`MergeReport`] describes every merged network (its size, first and last address,
+//! and the input networks it absorbed) along with totals for the whole run. It
+//! implements [`serde::Serialize`] so that it can be emitted as JSON for automation.
+
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+use serde::Serialize;
+
+use crate::provenance::provenance;
+use crate::{AnnotatedNet, MergeKind, ip_address_count, unique_values};
+
+/// Description of a complete merge run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MergeReport {
+    /// Number of input networks, including duplicates.
+    pub input_count: usize,
+    /// Number of merged networks.
+    pub output_count: usize,
+    /// Number of addresses covered by the output but by none of the inputs.
+    ///
+    /// Always 0 for lossless merges; non-zero only when tolerance was used.
+    pub extra_addresses: u128,
+    /// The merged networks, in output order.
+    pub networks: Vec<NetworkReport>,
+}
+
+/// Description of a single merged network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkReport {
+    /// The merged network.
+    pub network: IpNet,
+    /// How the network was produced from its inputs.
+    pub kind: MergeKind,
+    /// Number of addresses in the network, including network and broadcast addresses.
+    pub address_count: u128,
+    /// First address of the network.
+    pub first_address: IpAddr,
+    /// Last address of the network.
+    pub last_address: IpAddr,
+    /// Distinct input networks covered by this network, in input order.
+    pub absorbed: Vec<IpNet>,
+    /// Combined annotations of the absorbed input networks, in input order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<String>,
+    /// Combined sources (such as file names) of the absorbed input networks, in input
+    /// order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+}
+
+impl MergeReport {
+    /// Builds a report for `merged`, the result of merging `inputs`.
+    ///
+    /// `merged` must be the output of one of the merge functions for exactly these
+    /// inputs (in any order), so that every input is covered by one merged network.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use clpsr::{merge_ip_nets, AnnotatedNet, MergeReport};
+    /// use ipnet::IpNet;
+    ///
+    /// let nets: Vec<IpNet> = vec!["10.0.0.0/24".parse().unwrap(), "10.0.2.0/24".parse().unwrap()];
+    /// let merged = merge_ip_nets(nets.clone(), 512, 0);
+    /// let inputs: Vec<AnnotatedNet> = nets.into_iter().map(AnnotatedNet::from).collect();
+    /// let report = MergeReport::new(&inputs, &merged);
+    ///
+    /// assert_eq!(report.output_count, 1);
+    /// assert_eq!(report.extra_addresses, 512);
+    /// assert_eq!(report.networks[0].absorbed.len(), 2);
+    /// ```
+    pub fn new(inputs: &[AnnotatedNet], merged: &[IpNet]) -> Self {
+        let input_nets: Vec<IpNet> = inputs.iter().map(|entry| entry.net).collect();
+
+        let mut extra_addresses: u128 = 0;
+        let networks: Vec<NetworkReport> = provenance(&input_nets, merged)
+            .into_iter()
+            .map(|merged_net| {
+                extra_addresses = extra_addresses.saturating_add(merged_net.extra_addresses);
+
+                let indices = &merged_net.inputs;
+                let annotations =
+                    unique_values(indices.iter().map(|&idx| &inputs[idx].annotations));
+                let sources = unique_values(indices.iter().map(|&idx| &inputs[idx].sources));
+
+                let network = merged_net.net;
+                NetworkReport {
+                    network,
+                    kind: merged_net.kind,
+                    address_count: ip_address_count(&network),
+                    first_address: network.network(),
+                    last_address: network.broadcast(),
+                    absorbed: merged_net.covered,
+                    annotations,
+                    sources,
+                }
+            })
+            .collect();
+
+        MergeReport {
+            input_count: inputs.len(),
+            output_count: merged.len(),
+            extra_addresses,
+            networks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn inputs(list: &[&str]) -> Vec<AnnotatedNet> {
+        nets(list).into_iter().map(AnnotatedNet::from).collect()
+    }
+
+    #[test]
+    fn report_describes_each_merged_network() {
+        let input = inputs(&["10.0.1.0/24", "10.0.0.0/24", "10.0.0.0/24", "2001:db8::/48"]);
+        let merged = nets(&["10.0.0.0/23", "2001:db8::/48"]);
+
+        let report = MergeReport::new(&input, &merged);
+
+        assert_eq!(report.input_count, 4);
+        assert_eq!(report.output_count, 2);
+        assert_eq!(report.extra_addresses, 0);
+        assert_eq!(
+            report.networks[0],
+            NetworkReport {
+                network: merged[0],
+                kind: MergeKind::ExactMerge,
+                address_count: 512,
+                first_address: "10.0.0.0".parse().unwrap(),
+                last_address: "10.0.1.255".parse().unwrap(),
+                absorbed: nets(&["10.0.1.0/24", "10.0.0.0/24"]),
+                annotations: vec![],
+                sources: vec![],
+            }
+        );
+        assert_eq!(report.networks[1].address_count, 1u128 << 80);
+        assert_eq!(
+            report.networks[1].last_address,
+            "2001:db8:0:ffff:ffff:ffff:ffff:ffff"
+                .parse::<IpAddr>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn report_counts_extra_addresses_from_tolerance() {
+        // 10.0.0.0/22 covers 1024 addresses, of which the inputs cover 256 + 256
+        let input = inputs(&["10.0.0.0/24", "10.0.2.0/24", "10.0.2.128/25"]);
+        let merged = nets(&["10.0.0.0/22"]);
+
+        let report = MergeReport::new(&input, &merged);
+
+        assert_eq!(report.extra_addresses, 512);
+    }
+
+    #[test]
+    fn report_serializes_to_json() {
+        let input = vec![
+            AnnotatedNet {
+                net: "10.0.0.0/24".parse().unwrap(),
+                annotations: vec!["prod".to_string()],
+                sources: vec!["a.txt".to_string()],
+            },
+            AnnotatedNet::from("10.0.1.0/24".parse::<IpNet>().unwrap()),
+        ];
+        let merged = nets(&["10.0.0.0/23"]);
+
+        let json = serde_json::to_value(MergeReport::new(&input, &merged)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "input_count": 2,
+                "output_count": 1,
+                "extra_addresses": 0,
+                "networks": [{
+                    "network": "10.0.0.0/23",
+                    "kind": "exact_merge",
+                    "address_count": 512,
+                    "first_address": "10.0.0.0",
+                    "last_address": "10.0.1.255",
+                    "absorbed": ["10.0.0.0/24", "10.0.1.0/24"],
+                    "annotations": ["prod"],
+                    "sources": ["a.txt"],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn report_omits_empty_annotations() {
+        let input = inputs(&["10.0.0.0/24"]);
+        let json = serde_json::to_value(MergeReport::new(&input, &nets(&["10.0.0.0/24"]))).unwrap();
+
+        assert!(json["networks"][0].get("annotations").is_none());
+        assert!(json["networks"][0].get("sources").is_none());
+    }
+}
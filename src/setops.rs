@@ -0,0 +1,631 @@
+//! Set operations on lists of networks.
+//!
+//! The networks of a list are treated as the set of addresses they cover. Results
+//! are always returned as the minimal list of CIDRs covering exactly the resulting
+//! set, sorted by address, so they never depend on how the inputs were written.
+
+use std::net::IpAddr;
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+
+use crate::{
+    IpBlock, merge_nets, network_covers_impl, remove_covered_nets_impl, sort_and_dedup_impl,
+    split_by_family,
+};
+
+/// An inclusive range of addresses, widened to `u128`.
+type Range = (u128, u128);
+
+/// Subtracts one set of IPv4 networks from another.
+///
+/// Returns the minimal list of CIDRs covering every address of `nets` that is not
+/// covered by `exclude`. Networks are split as needed, so excluding part of a
+/// supernet yields the CIDRs around the hole.
+///
+/// # Arguments
+///
+/// * `nets` - Networks to subtract from
+/// * `exclude` - Networks to remove
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::exclude_ipv4_nets;
+/// use ipnet::Ipv4Net;
+///
+/// let nets: Vec<Ipv4Net> = vec!["10.0.0.0/22".parse().unwrap()];
+/// let exclude: Vec<Ipv4Net> = vec!["10.0.1.0/24".parse().unwrap()];
+/// let remaining = exclude_ipv4_nets(nets, exclude);
+///
+/// assert_eq!(remaining[0].to_string(), "10.0.0.0/24");
+/// assert_eq!(remaining[1].to_string(), "10.0.2.0/23");
+/// ```
+pub fn exclude_ipv4_nets(nets: Vec<Ipv4Net>, exclude: Vec<Ipv4Net>) -> Vec<Ipv4Net> {
+    exclude_nets(&nets, &exclude)
+}
+
+/// Subtracts one set of IPv6 networks from another.
+///
+/// See [`exclude_ipv4_nets`] for details.
+pub fn exclude_ipv6_nets(nets: Vec<Ipv6Net>, exclude: Vec<Ipv6Net>) -> Vec<Ipv6Net> {
+    exclude_nets(&nets, &exclude)
+}
+
+/// Subtracts one dual-stack set of networks from another.
+///
+/// Each family is handled separately; see [`exclude_ipv4_nets`] for details. The
+/// result lists all IPv4 networks first, then all IPv6 networks.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::exclude_ip_nets;
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = vec!["10.0.0.0/23".parse().unwrap(), "2001:db8::/32".parse().unwrap()];
+/// let exclude: Vec<IpNet> = vec!["10.0.0.0/24".parse().unwrap()];
+/// let remaining = exclude_ip_nets(nets, exclude);
+///
+/// assert_eq!(remaining.len(), 2);
+/// assert_eq!(remaining[0].to_string(), "10.0.1.0/24");
+/// assert_eq!(remaining[1].to_string(), "2001:db8::/32");
+/// ```
+pub fn exclude_ip_nets(nets: Vec<IpNet>, exclude: Vec<IpNet>) -> Vec<IpNet> {
+    let (v4, v6) = split_by_family(nets);
+    let (exclude_v4, exclude_v6) = split_by_family(exclude);
+
+    exclude_nets(&v4, &exclude_v4)
+        .into_iter()
+        .map(IpNet::V4)
+        .chain(exclude_nets(&v6, &exclude_v6).into_iter().map(IpNet::V6))
+        .collect()
+}
+
+/// Lists the free space left in an IPv4 container.
+///
+/// Returns the minimal list of aligned CIDRs covering every address of `container`
+/// that is not covered by `nets`. Networks outside the container are ignored, and
+/// networks that only partly overlap it take just their overlapping part.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::complement_ipv4_nets;
+/// use ipnet::Ipv4Net;
+///
+/// let container: Ipv4Net = "10.0.0.0/22".parse().unwrap();
+/// let allocated: Vec<Ipv4Net> = vec!["10.0.1.0/24".parse().unwrap()];
+/// let free = complement_ipv4_nets(container, allocated);
+///
+/// assert_eq!(free[0].to_string(), "10.0.0.0/24");
+/// assert_eq!(free[1].to_string(), "10.0.2.0/23");
+/// ```
+pub fn complement_ipv4_nets(container: Ipv4Net, nets: Vec<Ipv4Net>) -> Vec<Ipv4Net> {
+    exclude_nets(&[container], &nets)
+}
+
+/// Lists the free space left in an IPv6 container.
+///
+/// See [`complement_ipv4_nets`] for details.
+pub fn complement_ipv6_nets(container: Ipv6Net, nets: Vec<Ipv6Net>) -> Vec<Ipv6Net> {
+    exclude_nets(&[container], &nets)
+}
+
+/// Lists the free space left in a container of either family.
+///
+/// Networks of the other family than `container` are ignored; see
+/// [`complement_ipv4_nets`] for details.
+pub fn complement_ip_nets(container: IpNet, nets: Vec<IpNet>) -> Vec<IpNet> {
+    let (v4, v6) = split_by_family(nets);
+    match container {
+        IpNet::V4(container) => complement_ipv4_nets(container, v4)
+            .into_iter()
+            .map(IpNet::V4)
+            .collect(),
+        IpNet::V6(container) => complement_ipv6_nets(container, v6)
+            .into_iter()
+            .map(IpNet::V6)
+            .collect(),
+    }
+}
+
+/// Computes the union of two sets of IPv4 networks.
+///
+/// Returns the minimal list of CIDRs covering every address covered by `a` or `b`.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::union_ipv4_nets;
+/// use ipnet::Ipv4Net;
+///
+/// let a: Vec<Ipv4Net> = vec!["10.0.0.0/24".parse().unwrap()];
+/// let b: Vec<Ipv4Net> = vec!["10.0.1.0/24".parse().unwrap(), "10.0.0.128/25".parse().unwrap()];
+/// let union = union_ipv4_nets(a, b);
+///
+/// assert_eq!(union.len(), 1);
+/// assert_eq!(union[0].to_string(), "10.0.0.0/23");
+/// ```
+pub fn union_ipv4_nets(a: Vec<Ipv4Net>, b: Vec<Ipv4Net>) -> Vec<Ipv4Net> {
+    union_nets(a, b)
+}
+
+/// Computes the union of two sets of IPv6 networks.
+///
+/// See [`union_ipv4_nets`] for details.
+pub fn union_ipv6_nets(a: Vec<Ipv6Net>, b: Vec<Ipv6Net>) -> Vec<Ipv6Net> {
+    union_nets(a, b)
+}
+
+/// Computes the union of two dual-stack sets of networks.
+///
+/// Each family is handled separately; see [`union_ipv4_nets`] for details. The result
+/// lists all IPv4 networks first, then all IPv6 networks.
+pub fn union_ip_nets(a: Vec<IpNet>, b: Vec<IpNet>) -> Vec<IpNet> {
+    let (a_v4, a_v6) = split_by_family(a);
+    let (b_v4, b_v6) = split_by_family(b);
+
+    union_nets(a_v4, b_v4)
+        .into_iter()
+        .map(IpNet::V4)
+        .chain(union_nets(a_v6, b_v6).into_iter().map(IpNet::V6))
+        .collect()
+}
+
+/// Computes the intersection of two sets of IPv4 networks.
+///
+/// Returns the minimal list of CIDRs covering every address covered by both `a` and
+/// `b`. A network that is only partly covered by the other set contributes just the
+/// covered part.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::intersect_ipv4_nets;
+/// use ipnet::Ipv4Net;
+///
+/// let a: Vec<Ipv4Net> = vec!["10.0.0.0/16".parse().unwrap(), "192.168.0.0/24".parse().unwrap()];
+/// let b: Vec<Ipv4Net> = vec!["10.0.8.0/24".parse().unwrap(), "172.16.0.0/12".parse().unwrap()];
+/// let common = intersect_ipv4_nets(a, b);
+///
+/// assert_eq!(common.len(), 1);
+/// assert_eq!(common[0].to_string(), "10.0.8.0/24");
+/// ```
+pub fn intersect_ipv4_nets(a: Vec<Ipv4Net>, b: Vec<Ipv4Net>) -> Vec<Ipv4Net> {
+    intersect_nets(a, b)
+}
+
+/// Computes the intersection of two sets of IPv6 networks.
+///
+/// See [`intersect_ipv4_nets`] for details.
+pub fn intersect_ipv6_nets(a: Vec<Ipv6Net>, b: Vec<Ipv6Net>) -> Vec<Ipv6Net> {
+    intersect_nets(a, b)
+}
+
+/// Computes the intersection of two dual-stack sets of networks.
+///
+/// Each family is handled separately; see [`intersect_ipv4_nets`] for details. The
+/// result lists all IPv4 networks first, then all IPv6 networks.
+pub fn intersect_ip_nets(a: Vec<IpNet>, b: Vec<IpNet>) -> Vec<IpNet> {
+    let (a_v4, a_v6) = split_by_family(a);
+    let (b_v4, b_v6) = split_by_family(b);
+
+    intersect_nets(a_v4, b_v4)
+        .into_iter()
+        .map(IpNet::V4)
+        .chain(intersect_nets(a_v6, b_v6).into_iter().map(IpNet::V6))
+        .collect()
+}
+
+/// Family-generic implementation of the union functions.
+fn union_nets<N: IpBlock>(mut a: Vec<N>, b: Vec<N>) -> Vec<N> {
+    a.extend(b);
+    merge_nets(a, 0)
+}
+
+/// Family-generic implementation of the intersect functions.
+fn intersect_nets<N: IpBlock>(a: Vec<N>, b: Vec<N>) -> Vec<N> {
+    let (a, b) = (disjoint(a), disjoint(b));
+
+    // Two prefixes either nest or are disjoint, so every overlap is the smaller of a
+    // pair. Walk both sorted lists, always advancing past the network that ends first.
+    let mut common = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while let (Some(x), Some(y)) = (a.get(i), b.get(j)) {
+        if network_covers_impl(x, y) {
+            common.push(*y);
+            j += 1;
+        } else if network_covers_impl(y, x) {
+            common.push(*x);
+            i += 1;
+        } else if x.end() < y.start() {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    // The overlaps are sorted and disjoint, but may still combine into supernets
+    merge_nets(common, 0)
+}
+
+/// Sorts and deduplicates networks and drops those covered by another one, leaving a
+/// sorted list of disjoint networks.
+pub(crate) fn disjoint<N: IpBlock>(mut nets: Vec<N>) -> Vec<N> {
+    sort_and_dedup_impl(&mut nets);
+    remove_covered_nets_impl(nets).0
+}
+
+/// Collapses networks into contiguous address ranges.
+///
+/// Returns the first and last address of every maximal run of addresses covered by
+/// `nets`, so overlapping and adjacent networks end up in a single range even when no
+/// single CIDR could cover them. IPv4 ranges come first, then IPv6 ranges, each sorted
+/// by address.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::ip_nets_to_ranges;
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = vec!["10.0.0.1/32".parse().unwrap(), "10.0.0.2/31".parse().unwrap()];
+/// let ranges = ip_nets_to_ranges(nets);
+///
+/// assert_eq!(ranges.len(), 1);
+/// assert_eq!(ranges[0].0.to_string(), "10.0.0.1");
+/// assert_eq!(ranges[0].1.to_string(), "10.0.0.3");
+/// ```
+pub fn ip_nets_to_ranges(nets: Vec<IpNet>) -> Vec<(IpAddr, IpAddr)> {
+    let (v4, v6) = split_by_family(nets);
+
+    let mut ranges = ip_ranges(&v4);
+    ranges.extend(ip_ranges(&v6));
+    ranges
+}
+
+/// Family-generic implementation of [`ip_nets_to_ranges`].
+fn ip_ranges<N: IpBlock + Into<IpNet>>(nets: &[N]) -> Vec<(IpAddr, IpAddr)> {
+    let address = |value: u128| -> IpAddr {
+        N::from_start(value, N::BITS)
+            .expect("a single address is always aligned")
+            .into()
+            .addr()
+    };
+
+    to_ranges(nets)
+        .into_iter()
+        .map(|(start, end)| (address(start), address(end)))
+        .collect()
+}
+
+/// Family-generic implementation of the exclude functions.
+fn exclude_nets<N: IpBlock>(nets: &[N], exclude: &[N]) -> Vec<N> {
+    ranges_to_nets(&subtract_ranges(&to_ranges(nets), &to_ranges(exclude)))
+}
+
+/// Converts networks into sorted, non-overlapping, non-adjacent address ranges.
+fn to_ranges<N: IpBlock>(nets: &[N]) -> Vec<Range> {
+    let mut ranges: Vec<Range> = nets.iter().map(|net| (net.start(), net.end())).collect();
+    ranges.sort_unstable();
+
+    let mut coalesced: Vec<Range> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        if let Some(last) = coalesced.last_mut()
+            && start <= last.1.saturating_add(1)
+        {
+            last.1 = last.1.max(end);
+            continue;
+        }
+        coalesced.push((start, end));
+    }
+    coalesced
+}
+
+/// Removes the addresses of `remove` from `ranges`.
+///
+/// Both inputs must be sorted and non-overlapping, as returned by `to_ranges`.
+fn subtract_ranges(ranges: &[Range], remove: &[Range]) -> Vec<Range> {
+    let mut result = Vec::with_capacity(ranges.len());
+    let mut remove = remove.iter().peekable();
+
+    for &(mut start, end) in ranges {
+        // Skip removals that end before this range starts
+        while remove
+            .next_if(|&&(_, remove_end)| remove_end < start)
+            .is_some()
+        {}
+
+        let mut fully_removed = false;
+        while let Some(&&(remove_start, remove_end)) = remove.peek() {
+            if remove_start > end {
+                break;
+            }
+            if remove_start > start {
+                result.push((start, remove_start - 1));
+            }
+            if remove_end >= end {
+                fully_removed = true;
+                break;
+            }
+            start = remove_end + 1;
+            remove.next();
+        }
+
+        if !fully_removed {
+            result.push((start, end));
+        }
+    }
+    result
+}
+
+/// Converts sorted, non-overlapping address ranges into the minimal list of CIDRs
+/// covering them.
+fn ranges_to_nets<N: IpBlock>(ranges: &[Range]) -> Vec<N> {
+    let mut nets = Vec::new();
+    for &(start, end) in ranges {
+        range_to_nets(start, end, &mut nets);
+    }
+    nets
+}
+
+/// Appends the minimal list of CIDRs covering `start..=end` to `nets`.
+///
+/// Each CIDR is the largest block that starts at the current address, is aligned to
+/// its own size, and does not extend past `end`.
+pub(crate) fn range_to_nets<N: IpBlock>(mut start: u128, end: u128, nets: &mut Vec<N>) {
+    loop {
+        // Largest block size (as a power of two) that fits in the remaining range
+        let remaining = end - start;
+        let fits = match remaining.checked_add(1) {
+            Some(len) => 127 - len.leading_zeros(),
+            None => 128,
+        };
+        let aligned = start.trailing_zeros();
+        let host_bits = fits.min(aligned).min(u32::from(N::BITS));
+
+        let prefix = N::BITS - host_bits as u8;
+        nets.push(N::from_start(start, prefix).expect("prefix is within range"));
+
+        match 1u128
+            .checked_shl(host_bits)
+            .and_then(|size| start.checked_add(size))
+        {
+            Some(next) if next <= end => start = next,
+            _ => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(list: &[&str]) -> Vec<Ipv4Net> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn v6(list: &[&str]) -> Vec<Ipv6Net> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn ip_net(s: &str) -> IpNet {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn exclude_splits_supernet_around_hole() {
+        let remaining = exclude_ipv4_nets(v4(&["10.0.0.0/8"]), v4(&["10.1.2.0/24"]));
+
+        // 10.0.0.0/16 and 10.1.0.0/23 below the hole, 14 blocks above it
+        assert_eq!(remaining.len(), 16);
+        assert_eq!(
+            &remaining[..3],
+            &v4(&["10.0.0.0/16", "10.1.0.0/23", "10.1.3.0/24"])
+        );
+        assert_eq!(remaining[15], "10.128.0.0/9".parse().unwrap());
+        assert!(
+            remaining
+                .iter()
+                .all(|net| !net.contains(&"10.1.2.7".parse::<std::net::Ipv4Addr>().unwrap()))
+        );
+        let total: u128 = remaining
+            .iter()
+            .map(|net| 1u128 << (32 - net.prefix_len()))
+            .sum();
+        assert_eq!(total, (1 << 24) - 256);
+    }
+
+    #[test]
+    fn exclude_returns_minimal_sorted_output() {
+        let remaining = exclude_ipv4_nets(
+            v4(&["10.0.3.0/24", "10.0.0.0/24", "10.0.1.0/24", "10.0.2.0/24"]),
+            v4(&["192.168.0.0/16"]),
+        );
+
+        assert_eq!(remaining, v4(&["10.0.0.0/22"]));
+    }
+
+    #[test]
+    fn exclude_handles_overlapping_and_adjacent_exclusions() {
+        let remaining = exclude_ipv4_nets(
+            v4(&["10.0.0.0/22"]),
+            v4(&[
+                "10.0.0.0/24",
+                "10.0.0.128/25",
+                "10.0.1.0/24",
+                "10.0.3.128/25",
+            ]),
+        );
+
+        assert_eq!(remaining, v4(&["10.0.2.0/24", "10.0.3.0/25"]));
+    }
+
+    #[test]
+    fn exclude_everything_leaves_nothing() {
+        assert!(exclude_ipv4_nets(v4(&["10.0.0.0/24"]), v4(&["10.0.0.0/8"])).is_empty());
+        assert!(exclude_ipv4_nets(vec![], v4(&["10.0.0.0/8"])).is_empty());
+    }
+
+    #[test]
+    fn exclude_handles_whole_address_space() {
+        assert_eq!(
+            exclude_ipv6_nets(v6(&["::/0"]), v6(&["8000::/1"])),
+            v6(&["::/1"])
+        );
+        assert_eq!(exclude_ipv6_nets(v6(&["::/0"]), vec![]), v6(&["::/0"]));
+        assert_eq!(
+            exclude_ipv4_nets(v4(&["0.0.0.0/0"]), v4(&["255.255.255.255/32"])).len(),
+            32
+        );
+    }
+
+    #[test]
+    fn exclude_ip_nets_keeps_families_apart() {
+        let nets: Vec<IpNet> = vec![
+            "10.0.0.0/23".parse().unwrap(),
+            "2001:db8::/47".parse().unwrap(),
+        ];
+        let exclude: Vec<IpNet> = vec!["2001:db8::/48".parse().unwrap(), "::/0".parse().unwrap()];
+
+        assert_eq!(exclude_ip_nets(nets.clone(), exclude), vec![nets[0]]);
+    }
+
+    #[test]
+    fn complement_lists_free_space_in_container() {
+        let free = complement_ipv4_nets(
+            "10.0.0.0/16".parse().unwrap(),
+            v4(&[
+                "10.0.0.0/24",
+                "10.0.1.0/24",
+                "10.0.128.0/17",
+                "10.0.3.128/25",
+                "192.168.0.0/16",
+            ]),
+        );
+
+        assert_eq!(
+            free,
+            v4(&[
+                "10.0.2.0/24",
+                "10.0.3.0/25",
+                "10.0.4.0/22",
+                "10.0.8.0/21",
+                "10.0.16.0/20",
+                "10.0.32.0/19",
+                "10.0.64.0/18",
+            ])
+        );
+    }
+
+    #[test]
+    fn complement_handles_partial_overlap_and_full_cover() {
+        // An allocation larger than the container leaves nothing free
+        assert!(
+            complement_ipv4_nets("10.0.0.0/24".parse().unwrap(), v4(&["10.0.0.0/8"])).is_empty()
+        );
+        // Nothing allocated leaves the whole container free
+        assert_eq!(
+            complement_ipv6_nets("::/0".parse().unwrap(), vec![]),
+            v6(&["::/0"])
+        );
+        assert_eq!(
+            complement_ip_nets(
+                ip_net("0.0.0.0/0"),
+                vec![ip_net("::/0"), ip_net("128.0.0.0/1")]
+            ),
+            vec![ip_net("0.0.0.0/1")]
+        );
+    }
+
+    #[test]
+    fn union_returns_minimal_output() {
+        let union = union_ipv4_nets(
+            v4(&["10.0.0.0/24", "10.0.2.0/24", "10.0.0.0/25"]),
+            v4(&["10.0.3.0/24", "10.0.1.0/24", "192.168.0.0/24"]),
+        );
+
+        assert_eq!(union, v4(&["10.0.0.0/22", "192.168.0.0/24"]));
+    }
+
+    #[test]
+    fn intersect_keeps_the_smaller_side_of_every_overlap() {
+        let common = intersect_ipv4_nets(
+            v4(&["10.0.0.0/16", "10.1.0.0/25", "10.2.0.0/24"]),
+            v4(&["10.0.4.0/23", "10.0.6.0/23", "10.1.0.0/16", "10.2.1.0/24"]),
+        );
+
+        assert_eq!(common, v4(&["10.0.4.0/22", "10.1.0.0/25"]));
+    }
+
+    #[test]
+    fn intersect_handles_duplicates_covered_and_empty_inputs() {
+        assert_eq!(
+            intersect_ipv4_nets(
+                v4(&["10.0.0.0/24", "10.0.0.0/24", "10.0.0.64/26"]),
+                v4(&["10.0.0.0/8"])
+            ),
+            v4(&["10.0.0.0/24"])
+        );
+        assert!(intersect_ipv4_nets(v4(&["10.0.0.0/24"]), vec![]).is_empty());
+        assert_eq!(
+            intersect_ipv6_nets(v6(&["::/0"]), v6(&["2001:db8::/32"])),
+            v6(&["2001:db8::/32"])
+        );
+    }
+
+    #[test]
+    fn intersect_ip_nets_keeps_families_apart() {
+        let a: Vec<IpNet> = vec![
+            "0.0.0.0/0".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let b: Vec<IpNet> = vec!["::/0".parse().unwrap(), "10.0.0.0/8".parse().unwrap()];
+
+        assert_eq!(
+            intersect_ip_nets(a, b),
+            vec![ip_net("10.0.0.0/8"), ip_net("2001:db8::/32")]
+        );
+    }
+
+    #[test]
+    fn ranges_collapse_adjacent_and_overlapping_networks() {
+        let ranges = ip_nets_to_ranges(vec![
+            ip_net("2001:db8::/127"),
+            ip_net("10.0.0.8/29"),
+            ip_net("10.0.0.1/32"),
+            ip_net("10.0.0.2/31"),
+            ip_net("10.0.0.4/30"),
+            ip_net("10.0.0.12/30"),
+            ip_net("10.0.1.0/24"),
+        ]);
+
+        let printed: Vec<String> = ranges
+            .iter()
+            .map(|(start, end)| format!("{start}-{end}"))
+            .collect();
+        assert_eq!(
+            printed,
+            [
+                "10.0.0.1-10.0.0.15",
+                "10.0.1.0-10.0.1.255",
+                "2001:db8::-2001:db8::1"
+            ]
+        );
+    }
+
+    #[test]
+    fn range_to_nets_decomposes_unaligned_ranges() {
+        let mut nets: Vec<Ipv4Net> = Vec::new();
+        range_to_nets(
+            u128::from(u32::from(std::net::Ipv4Addr::new(10, 0, 0, 1))),
+            u128::from(u32::from(std::net::Ipv4Addr::new(10, 0, 0, 6))),
+            &mut nets,
+        );
+
+        assert_eq!(
+            nets,
+            v4(&["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"])
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Splitting networks into fixed-size subnets.
+//!
+//! Splitting is the inverse of merging: every network is expanded into the subnets of
+//! a given prefix length that cover it. Subnets are produced lazily, so even very large
+//! expansions use a constant amount of memory beyond the input itself.
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net, PrefixLenError};
+
+use crate::setops::disjoint;
+use crate::{IpBlock, split_by_family};
+
+/// Splits IPv4 networks into subnets with the given prefix length.
+///
+/// The inputs are sorted and deduplicated first, and networks covered by another input
+/// are dropped, so the subnets come out sorted and each appears once. Networks that are
+/// already at least as long as `prefix` are passed through unchanged.
+///
+/// Use [`split_count`] to find out how many subnets a split produces before running it.
+///
+/// # Errors
+///
+/// Returns [`PrefixLenError`] if `prefix` is greater than 32.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::split_ipv4_nets;
+/// use ipnet::Ipv4Net;
+///
+/// let nets: Vec<Ipv4Net> = vec!["10.0.0.0/23".parse().unwrap(), "10.0.8.0/26".parse().unwrap()];
+/// let subnets: Vec<Ipv4Net> = split_ipv4_nets(nets, 24).unwrap().collect();
+///
+/// assert_eq!(subnets.len(), 3);
+/// assert_eq!(subnets[0].to_string(), "10.0.0.0/24");
+/// assert_eq!(subnets[1].to_string(), "10.0.1.0/24");
+/// assert_eq!(subnets[2].to_string(), "10.0.8.0/26");
+/// ```
+pub fn split_ipv4_nets(
+    nets: Vec<Ipv4Net>,
+    prefix: u8,
+) -> Result<impl Iterator<Item = Ipv4Net>, PrefixLenError> {
+    split_nets(nets, prefix)
+}
+
+/// Splits IPv6 networks into subnets with the given prefix length.
+///
+/// See [`split_ipv4_nets`] for details.
+///
+/// # Errors
+///
+/// Returns [`PrefixLenError`] if `prefix` is greater than 128.
+pub fn split_ipv6_nets(
+    nets: Vec<Ipv6Net>,
+    prefix: u8,
+) -> Result<impl Iterator<Item = Ipv6Net>, PrefixLenError> {
+    split_nets(nets, prefix)
+}
+
+/// Splits dual-stack networks into subnets, with a separate prefix length per family.
+///
+/// A family whose prefix length is `None` is passed through (sorted, deduplicated and
+/// without covered networks) but not split. See [`split_ipv4_nets`] for details. All
+/// IPv4 subnets come first, then all IPv6 subnets.
+///
+/// # Errors
+///
+/// Returns [`PrefixLenError`] if a prefix length is too long for its family.
+pub fn split_ip_nets(
+    nets: Vec<IpNet>,
+    prefix_v4: Option<u8>,
+    prefix_v6: Option<u8>,
+) -> Result<impl Iterator<Item = IpNet>, PrefixLenError> {
+    let (v4, v6) = split_by_family(nets);
+
+    // Every network is at least as long as /0, so splitting into /0 changes nothing
+    let v4 = split_nets(v4, prefix_v4.unwrap_or(0))?;
+    let v6 = split_nets(v6, prefix_v6.unwrap_or(0))?;
+    Ok(v4.map(IpNet::V4).chain(v6.map(IpNet::V6)))
+}
+
+/// Returns the number of subnets [`split_ip_nets`] would produce for the same arguments.
+///
+/// Prefix lengths that are too long for their family are treated as the longest valid
+/// one. The count saturates at `u128::MAX`.
+///
+/// # Examples
+///
+/// ```
+/// use clpsr::split_count;
+/// use ipnet::IpNet;
+///
+/// let nets: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap(), "2001:db8::/32".parse().unwrap()];
+///
+/// assert_eq!(split_count(&nets, Some(24), None), 65_537);
+/// assert_eq!(split_count(&nets, Some(32), Some(64)), (1 << 24) + (1 << 32));
+/// ```
+pub fn split_count(nets: &[IpNet], prefix_v4: Option<u8>, prefix_v6: Option<u8>) -> u128 {
+    let (v4, v6) = split_by_family(nets.to_vec());
+
+    count_subnets(v4, prefix_v4.unwrap_or(0))
+        .saturating_add(count_subnets(v6, prefix_v6.unwrap_or(0)))
+}
+
+/// Family-generic implementation of the split functions.
+fn split_nets<N: IpBlock>(
+    nets: Vec<N>,
+    prefix: u8,
+) -> Result<impl Iterator<Item = N>, PrefixLenError> {
+    if prefix > N::BITS {
+        return Err(PrefixLenError);
+    }
+    Ok(disjoint(nets)
+        .into_iter()
+        .flat_map(move |net| subnets(net, prefix)))
+}
+
+/// Family-generic implementation of [`split_count`].
+fn count_subnets<N: IpBlock>(nets: Vec<N>, prefix: u8) -> u128 {
+    let prefix = prefix.min(N::BITS);
+    disjoint(nets)
+        .iter()
+        .map(|net| {
+            1u128
+                .checked_shl(u32::from(prefix.saturating_sub(net.prefix())))
+                .unwrap_or(u128::MAX)
+        })
+        .fold(0, u128::saturating_add)
+}
+
+/// Returns the subnets of `net` with the given prefix length, in address order, or just
+/// `net` if it is already at least that long.
+fn subnets<N: IpBlock>(net: N, prefix: u8) -> impl Iterator<Item = N> {
+    let prefix = prefix.max(net.prefix());
+    // Only ::/0 kept whole has a step of 2^128, which does not fit; it has one subnet
+    let step = 1u128.checked_shl(u32::from(N::BITS - prefix)).unwrap_or(0);
+    let end = net.end();
+
+    std::iter::successors(Some(net.start()), move |&start| {
+        start
+            .checked_add(step)
+            .filter(|&next| step > 0 && next <= end)
+    })
+    .map(move |start| N::from_start(start, prefix).expect("prefix is within range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(list: &[&str]) -> Vec<Ipv4Net> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn split_expands_each_network_in_order() {
+        let subnets: Vec<Ipv4Net> = split_ipv4_nets(
+            v4(&["10.0.4.0/23", "10.0.0.0/22", "10.0.1.0/24", "10.0.8.0/25"]),
+            24,
+        )
+        .unwrap()
+        .collect();
+
+        assert_eq!(
+            subnets,
+            v4(&[
+                "10.0.0.0/24",
+                "10.0.1.0/24",
+                "10.0.2.0/24",
+                "10.0.3.0/24",
+                "10.0.4.0/24",
+                "10.0.5.0/24",
+                "10.0.8.0/25",
+            ])
+        );
+    }
+
+    #[test]
+    fn split_rejects_prefix_longer_than_family() {
+        assert!(split_ipv4_nets(v4(&["10.0.0.0/8"]), 33).is_err());
+        assert!(split_ipv6_nets(vec![], 128).is_ok());
+    }
+
+    #[test]
+    fn split_streams_huge_expansions() {
+        let all: Ipv6Net = "::/0".parse().unwrap();
+        let mut subnets = split_ipv6_nets(vec![all], 128).unwrap();
+
+        assert_eq!(subnets.next(), Some("::/128".parse().unwrap()));
+        assert_eq!(subnets.next(), Some("::1/128".parse().unwrap()));
+        assert_eq!(split_count(&[IpNet::V6(all)], None, Some(128)), u128::MAX);
+    }
+
+    #[test]
+    fn split_reaches_the_end_of_the_address_space() {
+        let subnets: Vec<Ipv4Net> = split_ipv4_nets(v4(&["255.255.255.252/30"]), 32)
+            .unwrap()
+            .collect();
+
+        assert_eq!(subnets.len(), 4);
+        assert_eq!(subnets[3], "255.255.255.255/32".parse().unwrap());
+    }
+
+    #[test]
+    fn split_ip_nets_leaves_family_without_prefix_alone() {
+        let nets: Vec<IpNet> = vec![
+            "2001:db8::/32".parse().unwrap(),
+            "10.0.0.0/23".parse().unwrap(),
+            "2001:db8::/48".parse().unwrap(),
+        ];
+
+        let subnets: Vec<String> = split_ip_nets(nets.clone(), Some(24), None)
+            .unwrap()
+            .map(|net| net.to_string())
+            .collect();
+
+        assert_eq!(subnets, ["10.0.0.0/24", "10.0.1.0/24", "2001:db8::/32"]);
+        assert_eq!(split_count(&nets, Some(24), None), 3);
+    }
+}
@@ -0,0 +1,99 @@
+//! Counters describing what a merge run did.
+
+use std::fmt;
+
+use serde::Serialize;
+
+/// Statistics collected while merging.
+///
+/// Returned by [`merge_ip_nets_with_stats`](crate::merge_ip_nets_with_stats). For
+/// dual-stack input the IPv4 and IPv6 counters are added together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MergeStats {
+    /// Number of input networks, including duplicates.
+    pub input_count: usize,
+    /// Number of networks in the merged output.
+    pub output_count: usize,
+    /// Number of input networks dropped because they repeated an earlier input.
+    pub duplicates_dropped: usize,
+    /// Number of networks removed because another network already covered them.
+    pub covered_removed: usize,
+    /// Number of pairwise merges that introduced no extra addresses.
+    pub exact_merges: usize,
+    /// Number of pairwise merges that introduced extra addresses.
+    pub tolerance_merges: usize,
+    /// Number of addresses covered by the output but by none of the inputs.
+    pub extra_addresses: u128,
+    /// Number of passes of the merge loop, summed over both address families.
+    pub passes: usize,
+    /// Number of distinct addresses covered by the input.
+    pub addresses_before: u128,
+    /// Number of addresses covered by the output.
+    pub addresses_after: u128,
+}
+
+impl MergeStats {
+    /// Adds the counters of `other` to `self`, saturating the address counts.
+    pub(crate) fn add(&mut self, other: &MergeStats) {
+        self.input_count += other.input_count;
+        self.output_count += other.output_count;
+        self.duplicates_dropped += other.duplicates_dropped;
+        self.covered_removed += other.covered_removed;
+        self.exact_merges += other.exact_merges;
+        self.tolerance_merges += other.tolerance_merges;
+        self.extra_addresses = self.extra_addresses.saturating_add(other.extra_addresses);
+        self.passes += other.passes;
+        self.addresses_before = self.addresses_before.saturating_add(other.addresses_before);
+        self.addresses_after = self.addresses_after.saturating_add(other.addresses_after);
+    }
+}
+
+impl fmt::Display for MergeStats {
+    /// Formats the statistics as aligned `label: value` lines.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "input networks:     {}", self.input_count)?;
+        writeln!(f, "output networks:    {}", self.output_count)?;
+        writeln!(f, "duplicates dropped: {}", self.duplicates_dropped)?;
+        writeln!(f, "covered removed:    {}", self.covered_removed)?;
+        writeln!(f, "exact merges:       {}", self.exact_merges)?;
+        writeln!(f, "tolerance merges:   {}", self.tolerance_merges)?;
+        writeln!(f, "extra addresses:    {}", self.extra_addresses)?;
+        writeln!(f, "passes:             {}", self.passes)?;
+        writeln!(f, "addresses before:   {}", self.addresses_before)?;
+        write!(f, "addresses after:    {}", self.addresses_after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_sums_counters_and_saturates_addresses() {
+        let mut total = MergeStats {
+            input_count: 2,
+            passes: 1,
+            addresses_before: u128::MAX,
+            ..MergeStats::default()
+        };
+        total.add(&MergeStats {
+            input_count: 3,
+            passes: 2,
+            addresses_before: 1,
+            ..MergeStats::default()
+        });
+
+        assert_eq!(total.input_count, 5);
+        assert_eq!(total.passes, 3);
+        assert_eq!(total.addresses_before, u128::MAX);
+    }
+
+    #[test]
+    fn display_lists_every_counter() {
+        let text = MergeStats::default().to_string();
+
+        assert_eq!(text.lines().count(), 10);
+        assert!(text.starts_with("input networks:     0\n"));
+        assert!(text.ends_with("addresses after:    0"));
+    }
+}
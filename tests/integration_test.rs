@@ -2,7 +2,7 @@ use std::io::Cursor;
 use std::process::Command;
 use std::str;
 
-use clpsr::{merge_ipv4_nets, parse_ipv4_nets};
+use clpsr::{merge_ip_nets, merge_ipv4_nets, parse_ip_nets, parse_ipv4_nets};
 
 #[test]
 fn test_end_to_end_parsing_and_merging() {
@@ -141,6 +141,17 @@ fn test_end_to_end_complex_scenario() {
     assert!(merged_strs.contains(&"172.16.0.0/16".to_string()));
 }
 
+#[test]
+fn test_end_to_end_dual_stack() {
+    let input = "2001:db8:1::/48\n10.0.0.0/24\n2001:db8::/48\n10.0.1.0/24\n2001:db8:1::/64";
+    let reader = Cursor::new(input);
+    let nets = parse_ip_nets(reader).unwrap();
+    let merged = merge_ip_nets(nets, 0, 0);
+
+    let merged_strs: Vec<String> = merged.iter().map(|n| n.to_string()).collect();
+    assert_eq!(merged_strs, vec!["10.0.0.0/23", "2001:db8::/47"]);
+}
+
 // TODO: Test the compiled binary instead of using "cargo run"
 #[test]
 fn test_cli_execution() {
@@ -348,3 +359,1158 @@ fn test_cli_check_mode_rejects_invalid_input() {
     assert!(stderr.contains("Line 3:"));
     assert!(stderr.contains("invalid IP address syntax"));
 }
+
+#[test]
+fn test_cli_dual_stack_with_per_family_tolerance() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--tolerance", "/22", "--tolerance-v6", "/62"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Each family has one gap that only its own tolerance can close
+        stdin
+            .write_all(b"2001:db8::/64\n10.0.0.0/24\n2001:db8:0:2::/64\n10.0.2.0/24\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stdout = str::from_utf8(&output.stdout).unwrap_or("");
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "10.0.0.0/22\n2001:db8::/62\n");
+}
+
+#[test]
+fn test_cli_ipv4_tolerance_does_not_apply_to_ipv6() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--tolerance", "/0"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"2001:db8::/64\n2001:db8:0:2::/64\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stdout = str::from_utf8(&output.stdout).unwrap_or("");
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "2001:db8::/64\n2001:db8:0:2::/64\n");
+}
+
+#[test]
+fn test_cli_on_error_skip_merges_valid_lines() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--on-error", "skip"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\njunk\n10.0.1.0/24\n10.0.2.0/33\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stdout = str::from_utf8(&output.stdout).unwrap_or("");
+    let stderr = str::from_utf8(&output.stderr).unwrap_or("");
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "10.0.0.0/23\n");
+    assert!(!stderr.contains("warning"));
+}
+
+#[test]
+fn test_cli_on_error_warn_reports_rejected_lines() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--on-error", "warn"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\njunk\n10.0.1.0/24\n10.0.2.0/33\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stdout = str::from_utf8(&output.stdout).unwrap_or("");
+    let stderr = str::from_utf8(&output.stderr).unwrap_or("");
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "10.0.0.0/23\n");
+    assert!(stderr.contains("Line 2: invalid IP address syntax"));
+    assert!(stderr.contains("Line 4: invalid prefix length"));
+    assert!(stderr.contains("skipped 2 invalid line(s)"));
+}
+
+#[test]
+fn test_cli_on_error_fail_is_default() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\njunk\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stderr = str::from_utf8(&output.stderr).unwrap_or("");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains("Line 2:"));
+}
+
+#[test]
+fn test_cli_ignores_comments() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"# firewall sources\n10.0.0.0/24  # prod-eu\n10.0.1.0/24 ; prod-us\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stdout = str::from_utf8(&output.stdout).unwrap_or("");
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "10.0.0.0/23\n");
+}
+
+#[test]
+fn test_cli_annotate_carries_comments_through_merge() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--annotate"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(
+                b"10.0.0.0/24  # prod-eu\n10.0.1.0/24 # prod-us\n10.0.1.0/24 # prod-us\n192.168.0.0/24\n",
+            )
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    let stdout = str::from_utf8(&output.stdout).unwrap_or("");
+
+    assert!(output.status.success());
+    assert_eq!(stdout, "10.0.0.0/23 # prod-eu, prod-us\n192.168.0.0/24\n");
+}
+
+#[test]
+fn test_cli_format_json() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--format", "json", "--tolerance", "512"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24 # a\n10.0.2.0/24\n10.0.2.0/24\n2001:db8::/64\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be valid JSON");
+    assert_eq!(json["input_count"], 4);
+    assert_eq!(json["output_count"], 2);
+    assert_eq!(json["extra_addresses"], 512);
+
+    let first = &json["networks"][0];
+    assert_eq!(first["network"], "10.0.0.0/22");
+    assert_eq!(first["address_count"], 1024);
+    assert_eq!(first["first_address"], "10.0.0.0");
+    assert_eq!(first["last_address"], "10.0.3.255");
+    assert_eq!(
+        first["absorbed"],
+        serde_json::json!(["10.0.0.0/24", "10.0.2.0/24"])
+    );
+    assert_eq!(first["annotations"], serde_json::json!(["a"]));
+
+    let second = &json["networks"][1];
+    assert_eq!(second["network"], "2001:db8::/64");
+    // 2^64 does not fit in a u64, so serde_json parses it back as a float
+    assert_eq!(second["address_count"].as_f64(), Some(2f64.powi(64)));
+}
+
+#[test]
+fn test_cli_stats_reported_on_stderr() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--stats", "--tolerance", "512"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\n10.0.0.0/24\n10.0.2.0/24\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "10.0.0.0/22\n");
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("input networks:     3\n"));
+    assert!(stderr.contains("duplicates dropped: 1\n"));
+    assert!(stderr.contains("tolerance merges:   1\n"));
+    assert!(stderr.contains("extra addresses:    512\n"));
+    assert!(stderr.contains("addresses before:   512\n"));
+    assert!(stderr.contains("addresses after:    1024\n"));
+}
+
+#[test]
+fn test_cli_budget_caps_total_over_coverage() {
+    use std::io::Write;
+
+    let input = b"10.0.0.0/24\n10.0.2.0/24\n10.0.4.0/24\n10.0.6.0/24\n";
+    let run = |extra_args: &[&str]| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "--tolerance", "512"])
+            .args(extra_args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input).expect("Failed to write to stdin");
+        }
+
+        let output = child.wait_with_output().expect("Failed to read output");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Per merge, both /22 merges fit and the /21 has 1024 extra addresses
+    assert_eq!(run(&[]), "10.0.0.0/21\n");
+    assert_eq!(
+        run(&["--budget"]),
+        "10.0.0.0/22\n10.0.4.0/24\n10.0.6.0/24\n"
+    );
+}
+
+#[test]
+fn test_cli_max_entries_reports_over_coverage() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--max-entries", "2"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\n10.0.2.0/24\n10.0.9.0/24\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "10.0.0.0/22\n10.0.9.0/24\n");
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("accepted 512 extra IPv4 address(es) to fit in 2 entries"));
+}
+
+#[test]
+fn test_cli_max_entries_counts_both_families_together() {
+    use std::io::Write;
+
+    let run = |max_entries: &str| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "--max-entries", max_entries])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(b"10.0.0.0/24\n10.0.2.0/24\n2001:db8::/48\n2001:db8:2::/48\n")
+                .expect("Failed to write to stdin");
+        }
+
+        child.wait_with_output().expect("Failed to read output")
+    };
+
+    let output = run("3");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/22\n2001:db8::/48\n2001:db8:2::/48\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("accepted 512 extra IPv4 address(es) to fit in 3 entries"));
+
+    let output = run("2");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/22\n2001:db8::/46\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(
+        "accepted 512 extra IPv4 address(es) and 2417851639229258349412352 extra IPv6 address(es)"
+    ));
+
+    let output = run("1");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--max-entries 1 cannot be met"));
+}
+
+#[test]
+fn test_cli_max_entries_conflicts_with_tolerance() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--max-entries", "2", "--tolerance", "512"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to run cargo run");
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_optimal_strategy_beats_greedy_within_entry_limit() {
+    use std::io::Write;
+
+    let input = b"10.0.0.64/27\n10.0.0.200/29\n10.0.2.0/25\n10.0.3.64/29\n10.0.3.224/28\n";
+    let run = |strategy: &str| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "--max-entries", "3", "--strategy", strategy])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input).expect("Failed to write to stdin");
+        }
+
+        let output = child.wait_with_output().expect("Failed to read output");
+        assert!(output.status.success());
+        (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    let (greedy, greedy_err) = run("greedy");
+    assert_eq!(greedy, "10.0.0.0/24\n10.0.2.0/25\n10.0.3.0/24\n");
+    assert!(greedy_err.contains("accepted 448 extra IPv4 address(es)"));
+
+    let (optimal, optimal_err) = run("optimal");
+    assert_eq!(optimal, "10.0.0.64/27\n10.0.0.200/29\n10.0.2.0/23\n");
+    assert!(optimal_err.contains("accepted 360 extra IPv4 address(es)"));
+}
+
+#[test]
+fn test_cli_exclude_is_never_recovered_by_tolerance() {
+    use std::io::Write;
+
+    let exclude_file =
+        std::env::temp_dir().join(format!("clpsr-exclude-{}.txt", std::process::id()));
+    std::fs::write(&exclude_file, "# reserved\n10.0.3.0/25\n")
+        .expect("Failed to write exclude file");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "-t",
+            "512",
+            "--exclude",
+            "10.0.1.0/24",
+            "--exclude",
+        ])
+        .arg(&exclude_file)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\n10.0.2.0/24\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    std::fs::remove_file(&exclude_file).ok();
+    assert!(output.status.success());
+    // The tolerance merge to 10.0.0.0/22 is split back around both exclusions
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/24\n10.0.2.0/24\n10.0.3.128/25\n"
+    );
+}
+
+#[test]
+fn test_cli_exclude_rejects_missing_file() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--exclude", "no-such-exclusions.txt"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to run cargo");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no-such-exclusions.txt"));
+}
+
+#[test]
+fn test_cli_exclude_reports_malformed_networks() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--exclude", "10.0.0.0/33"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to run cargo");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--exclude 10.0.0.0/33"));
+    assert!(stderr.contains("invalid prefix length"));
+    assert!(!stderr.contains("No such file"));
+}
+
+#[test]
+fn test_cli_intersect_and_union_subcommands() {
+    let dir = std::env::temp_dir();
+    let a = dir.join(format!("clpsr-setops-a-{}.txt", std::process::id()));
+    let b = dir.join(format!("clpsr-setops-b-{}.txt", std::process::id()));
+    let c = dir.join(format!("clpsr-setops-c-{}.txt", std::process::id()));
+    std::fs::write(&a, "10.0.0.0/16\n2001:db8::/32\n").expect("Failed to write list");
+    std::fs::write(&b, "10.0.8.0/24 # office\n10.0.9.0/24\n::/0\n").expect("Failed to write list");
+    std::fs::write(&c, "10.1.0.0/16\n").expect("Failed to write list");
+
+    let run = |args: &[&std::path::Path], command: &str| {
+        let output = Command::new("cargo")
+            .args(["run", "--", command])
+            .args(args)
+            .output()
+            .expect("Failed to run cargo");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let intersection = run(&[&a, &b], "intersect");
+    let union = run(&[&a, &b, &c], "union");
+    for path in [&a, &b, &c] {
+        std::fs::remove_file(path).ok();
+    }
+
+    assert_eq!(intersection, "10.0.8.0/23\n2001:db8::/32\n");
+    assert_eq!(union, "10.0.0.0/15\n::/0\n");
+}
+
+#[test]
+fn test_cli_subcommands_reject_merge_options() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--tolerance", "512", "union", "a.txt", "b.txt"])
+        .output()
+        .expect("Failed to run cargo");
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_complement_lists_free_space() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "complement", "--within", "10.0.0.0/8"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/9\n10.200.0.0/16 # lab\n2001:db8::/32\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.128.0.0/10\n10.192.0.0/13\n10.201.0.0/16\n10.202.0.0/15\n10.204.0.0/14\n10.208.0.0/12\n10.224.0.0/11\n"
+    );
+}
+
+#[test]
+fn test_cli_diff_compares_address_space() {
+    let dir = std::env::temp_dir();
+    let old = dir.join(format!("clpsr-diff-old-{}.txt", std::process::id()));
+    let new = dir.join(format!("clpsr-diff-new-{}.txt", std::process::id()));
+    let same = dir.join(format!("clpsr-diff-same-{}.txt", std::process::id()));
+    std::fs::write(&old, "10.0.0.0/22\n192.168.0.0/24\n").expect("Failed to write list");
+    std::fs::write(&new, "10.0.0.0/23\n10.0.3.0/24\n172.16.0.0/12\n")
+        .expect("Failed to write list");
+    std::fs::write(&same, "192.168.0.0/25\n192.168.0.128/25\n10.0.0.0/22\n")
+        .expect("Failed to write list");
+
+    let run = |args: &[&std::ffi::OsStr]| {
+        Command::new("cargo")
+            .args(["run", "--", "diff"])
+            .args(args)
+            .output()
+            .expect("Failed to run cargo")
+    };
+
+    let changed = run(&[old.as_os_str(), new.as_os_str()]);
+    let json = run(&[
+        "--format".as_ref(),
+        "json".as_ref(),
+        old.as_os_str(),
+        new.as_os_str(),
+    ]);
+    let unchanged = run(&[old.as_os_str(), same.as_os_str()]);
+    let missing = run(&[old.as_os_str(), "no-such-list.txt".as_ref()]);
+    for path in [&old, &new, &same] {
+        std::fs::remove_file(path).ok();
+    }
+
+    assert_eq!(changed.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(changed.stdout).unwrap(),
+        "- 10.0.2.0/24\n+ 172.16.0.0/12\n- 192.168.0.0/24\n"
+    );
+
+    assert_eq!(json.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(report["added"], serde_json::json!(["172.16.0.0/12"]));
+    assert_eq!(
+        report["removed"],
+        serde_json::json!(["10.0.2.0/24", "192.168.0.0/24"])
+    );
+    assert_eq!(report["removed_addresses"], 512);
+
+    assert_eq!(unchanged.status.code(), Some(0));
+    assert!(unchanged.stdout.is_empty());
+
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_split_expands_networks() {
+    use std::io::Write;
+
+    let run = |args: &[&str]| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "split"])
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(b"10.0.0.0/23\n10.0.8.0/25\n2001:db8::/63\n")
+                .expect("Failed to write to stdin");
+        }
+
+        child.wait_with_output().expect("Failed to read output")
+    };
+
+    let output = run(&["--prefix", "24", "--prefix-v6", "64"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/24\n10.0.1.0/24\n10.0.8.0/25\n2001:db8::/64\n2001:db8:0:1::/64\n"
+    );
+
+    let guarded = run(&["--prefix", "32", "--max-subnets", "100"]);
+    assert!(!guarded.status.success());
+    assert!(guarded.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&guarded.stderr).contains("641 subnets"));
+}
+
+#[test]
+fn test_cli_info_prints_aligned_columns_and_json() {
+    use std::io::Write;
+
+    let run = |args: &[&str]| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "--info"])
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(b"10.0.0.0/24\n10.0.1.0/24\n2001:db8::/64\n")
+                .expect("Failed to write to stdin");
+        }
+
+        let output = child.wait_with_output().expect("Failed to read output");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let table = run(&[]);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("network        address     broadcast"));
+    let row: Vec<&str> = lines[1].split_whitespace().collect();
+    assert_eq!(
+        row,
+        [
+            "10.0.0.0/23",
+            "10.0.0.0",
+            "10.0.1.255",
+            "10.0.0.1",
+            "10.0.1.254",
+            "255.255.254.0",
+            "0.0.1.255",
+            "512",
+            "510"
+        ]
+    );
+    // Columns line up across IPv4 and IPv6 rows
+    assert_eq!(lines[1].find("10.0.1.255"), lines[2].find('-'));
+
+    let json: serde_json::Value = serde_json::from_str(&run(&["--format", "json"])).unwrap();
+    assert_eq!(json[0]["usable_count"], 510);
+    assert_eq!(json[1]["broadcast"], serde_json::Value::Null);
+    assert_eq!(json[1]["wildcard"], "::ffff:ffff:ffff:ffff");
+}
+
+#[test]
+fn test_cli_accepts_ranges_and_prints_range_format() {
+    use std::io::Write;
+
+    let run = |args: &[&str]| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--"])
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(
+                    b"10.0.0.5-10.0.0.200 # vendor a\n10.0.0.201 - 10.0.1.3\n2001:db8::/127\n",
+                )
+                .expect("Failed to write to stdin");
+        }
+
+        let output = child.wait_with_output().expect("Failed to read output");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let cidrs = run(&[]);
+    assert!(cidrs.starts_with("10.0.0.5/32\n10.0.0.6/31\n10.0.0.8/29\n"));
+    assert!(cidrs.contains("\n10.0.0.128/25\n10.0.1.0/30\n"));
+
+    assert_eq!(
+        run(&["--format", "range"]),
+        "10.0.0.5-10.0.1.3\n2001:db8::-2001:db8::1\n"
+    );
+}
+
+#[test]
+fn test_cli_accepts_bare_addresses_and_dotted_masks() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(
+                b"10.0.0.0 255.255.255.0\n10.0.1.0/255.255.255.0\n10.0.2.0 0.0.1.255\n10.0.9.9\n",
+            )
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/22\n10.0.9.9/32\n"
+    );
+
+    let output = Command::new("cargo")
+        .args(["run", "--"])
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(b"10.0.0.0 255.0.255.0\n")?;
+            child.wait_with_output()
+        })
+        .expect("Failed to run cargo run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("non-contiguous netmask"));
+}
+
+#[test]
+fn test_cli_host_bits_policy() {
+    use std::io::Write;
+
+    let run = |args: &[&str]| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--"])
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(b"10.0.0.1/24\n10.0.0.0/24\n10.0.1.0/24\n")
+                .expect("Failed to write to stdin");
+        }
+
+        child.wait_with_output().expect("Failed to read output")
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "10.0.0.0/23\n");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("warning"));
+
+    let output = run(&["--host-bits", "warn"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "10.0.0.0/23\n");
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("warning: clearing Line 1: host bits set beyond the prefix length")
+    );
+
+    let output = run(&["--host-bits", "reject"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Line 1: host bits set"));
+
+    let output = run(&["--host-bits", "reject", "--on-error", "skip"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "10.0.0.0/23\n");
+}
+
+#[test]
+fn test_cli_extract_finds_addresses_in_free_text() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "--extract"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(
+                b"<li>Failed login from 203.0.113.9 port 22</li>\n\
+                  agent v1.2.3.4 (build 5.6.7.8.9) peer 203.0.113.8, subnet 10.0.0.1/24.\n",
+            )
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/24\n203.0.113.8/31\n"
+    );
+}
+
+#[test]
+fn test_cli_merges_multiple_inputs_with_sources() {
+    let dir = std::env::temp_dir().join(format!("clpsr-inputs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "10.0.0.0/24 # eu\n10.0.2.0/24\n").unwrap();
+    std::fs::write(dir.join("b.txt"), "10.0.1.0/24 # us\n").unwrap();
+    std::fs::write(dir.join("c.list"), "10.0.3.0/24\nbogus\n").unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--annotate", "--input"])
+        .args([path("a.txt"), path("b.txt")])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "10.0.0.0/23 # eu, us (from {a}, {b})\n10.0.2.0/24 # from {a}\n",
+            a = path("a.txt"),
+            b = path("b.txt")
+        )
+    );
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--input", &path("*.txt")])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/23\n10.0.2.0/24\n"
+    );
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "-i", &path("a.txt"), "-i", &path("c.list")])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("c.list: Line 2: invalid IP address syntax"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_reads_csv_column_with_annotations() {
+    let csv = std::env::temp_dir().join(format!("clpsr-inventory-{}.csv", std::process::id()));
+    std::fs::write(
+        &csv,
+        "name,subnet,site\nweb,10.0.0.0/24,\"eu, west\"\ndb,10.0.1.0/24,\nlb,,eu\n",
+    )
+    .expect("Failed to write CSV");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--input-format", "csv", "--column", "subnet"])
+        .args(["--annotate", "--input", csv.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/23 # name=web, site=eu, west, name=db\n"
+    );
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--input-format", "csv", "--column", "3"])
+        .args(["--input", csv.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Line 2: invalid IP address syntax"));
+
+    std::fs::remove_file(&csv).ok();
+}
+
+#[test]
+fn test_cli_csv_options_require_csv_input() {
+    for args in [&["--column", "subnet"][..], &["--no-header"]] {
+        let output = Command::new("cargo")
+            .args(["run", "--"])
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .expect("Failed to execute cargo run");
+
+        assert!(!output.status.success());
+        let expected = format!("{} requires --input-format csv", args[0]);
+        assert!(String::from_utf8_lossy(&output.stderr).contains(&expected));
+    }
+}
+
+#[test]
+fn test_cli_reads_json_and_yaml_documents() {
+    let dir = std::env::temp_dir();
+    let json = dir.join(format!("clpsr-ip-ranges-{}.json", std::process::id()));
+    let yaml = dir.join(format!("clpsr-ip-ranges-{}.yaml", std::process::id()));
+    std::fs::write(
+        &json,
+        r#"{"prefixes": [
+            {"ip_prefix": "52.94.76.0/23", "region": "us-west-2", "service": "EC2"},
+            {"ip_prefix": "52.94.78.0/23", "region": "us-west-2", "service": "EC2"},
+            {"ip_prefix": "3.5.140.0/22", "region": "ap-northeast-2", "service": "EC2"}
+        ]}"#,
+    )
+    .expect("Failed to write JSON");
+    std::fs::write(
+        &yaml,
+        "values:\n  - properties:\n      region: westeurope\n      addressPrefixes:\n        - 20.38.64.0/19\n        - 2603:1020::/47\n",
+    )
+    .expect("Failed to write YAML");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--input", json.to_str().unwrap()])
+        .args([
+            "--input-format",
+            "json",
+            "--select",
+            ".prefixes[].ip_prefix",
+        ])
+        .args(["--where", "region=us-west-2", "--where", "service=EC2"])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "52.94.76.0/22\n");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "--input",
+            yaml.to_str().unwrap(),
+            "--input-format",
+        ])
+        .args(["yaml", "--select", ".values[].properties.addressPrefixes[]"])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "20.38.64.0/19\n2603:1020::/47\n"
+    );
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--input", "https://example.com/ip-ranges.json"])
+        .args([
+            "--input-format",
+            "json",
+            "--select",
+            ".prefixes[].ip_prefix",
+        ])
+        .output()
+        .expect("Failed to execute cargo run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only local files"));
+
+    std::fs::remove_file(&json).ok();
+    std::fs::remove_file(&yaml).ok();
+}
+
+#[test]
+fn test_cli_writes_firewall_rule_files() {
+    let path = std::env::temp_dir().join(format!("clpsr-firewall-{}.txt", std::process::id()));
+    std::fs::write(&path, "10.0.0.0/24\n10.0.1.0/24\n192.0.2.7\n").unwrap();
+    let run = |args: &[&str]| {
+        Command::new("cargo")
+            .args(["run", "--", "--input", path.to_str().unwrap()])
+            .args(args)
+            .output()
+            .expect("Failed to execute cargo run")
+    };
+
+    let output = run(&[
+        "--format", "iptables", "--chain", "INPUT", "--target", "DROP",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "*filter\n-A INPUT -s 10.0.0.0/23 -j DROP\n-A INPUT -s 192.0.2.7/32 -j DROP\nCOMMIT\n"
+    );
+
+    let output = run(&["--format", "nft-set", "--set-name", "blocklist"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\t\ttype ipv4_addr\n\t\tflags interval\n"));
+    assert!(stdout.ends_with("add element ip filter blocklist { 10.0.0.0/23, 192.0.2.7/32 }\n"));
+
+    let output = run(&["--format", "ipset-restore", "--set-name", "foo"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "create foo hash:net family inet -exist\nadd foo 10.0.0.0/23 -exist\nadd foo 192.0.2.7/32 -exist\n"
+    );
+
+    // Each format needs its own options
+    let output = run(&["--format", "ipset-restore"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("--set-name")
+    );
+
+    std::fs::write(&path, "10.0.0.0/24\n2001:db8::/32\n").unwrap();
+    let output = run(&["--format", "nft-set", "--set-name", "blocklist"]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("only supports IPv4 networks")
+    );
+}
+
+#[test]
+fn test_cli_writes_prefix_lists_with_absorbed_bounds() {
+    use std::io::Write;
+
+    let run = |args: &[&str]| {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "--list-name", "PEER"])
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cargo run");
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(
+                    b"10.0.0.0/24\n10.0.1.0/25\n10.0.1.128/25\n192.0.2.0/24\n2001:db8::/48\n",
+                )
+                .expect("Failed to write to stdin");
+        }
+        child.wait_with_output().expect("Failed to read output")
+    };
+
+    let output = run(&["--format", "cisco", "--le", "absorbed", "--seq-step", "10"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ip prefix-list PEER seq 5 permit 10.0.0.0/23 le 25\n\
+         ip prefix-list PEER seq 15 permit 192.0.2.0/24\n\
+         ipv6 prefix-list PEER seq 5 permit 2001:db8::/48\n"
+    );
+
+    let output = run(&["--format", "junos", "--ge", "24"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "set policy-options route-filter-list PEER 10.0.0.0/23 prefix-length-range /24-/32\n\
+         set policy-options route-filter-list PEER 192.0.2.0/24 exact\n\
+         set policy-options route-filter-list PEER 2001:db8::/48 exact\n"
+    );
+
+    let output = run(&["--format", "bird", "--le", "absorbed"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "define PEER = [\n\t10.0.0.0/23{23,25},\n\t192.0.2.0/24\n];\n\
+         define PEER_v6 = [\n\t2001:db8::/48\n];\n"
+    );
+
+    let output = run(&["--format", "frr", "--ge", "28", "--le", "24"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("--ge 28 is longer than --le 24")
+    );
+}
+
+#[test]
+fn test_cli_exclude_accepts_input_notations_and_updates_stats() {
+    use std::io::Write;
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "--stats",
+            "--exclude",
+            "10.0.0.5",
+            "--exclude",
+            "10.0.1.0 255.255.255.128",
+        ])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn cargo run");
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(b"10.0.0.0/24\n10.0.1.0/24\n")
+            .expect("Failed to write to stdin");
+    }
+
+    let output = child.wait_with_output().expect("Failed to read output");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "10.0.0.0/30\n10.0.0.4/32\n10.0.0.6/31\n10.0.0.8/29\n10.0.0.16/28\n10.0.0.32/27\n\
+         10.0.0.64/26\n10.0.0.128/25\n10.0.1.128/25\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("output networks:    9\n"));
+    assert!(stderr.contains("addresses after:    383"));
+}